@@ -0,0 +1,42 @@
+use super::inspector::{estimate_tokens, push_key};
+use crate::logic::persistence::SavedMessage;
+use std::collections::HashSet;
+
+/// Forgets the oldest messages until the cache and the prompt about to be sent fit in
+/// `max_tokens`. The init prompt and pinned messages are never forgotten, so a budget too
+/// small for them gets exceeded. `None` if everything already fits
+pub fn trim_to_budget(
+    cache: &[SavedMessage],
+    prompt: &str,
+    max_tokens: usize,
+    pinned: &HashSet<u64>,
+) -> Option<Vec<SavedMessage>> {
+    let mut total = cache
+        .iter()
+        .map(|message| estimate_tokens(&message.content))
+        .sum::<usize>()
+        + estimate_tokens(prompt);
+    let mut keep = vec![true; cache.len()];
+    for (idx, message) in cache.iter().enumerate() {
+        if total <= max_tokens {
+            break;
+        }
+        let is_init_prompt = idx == 0 && message.role == "system";
+        if is_init_prompt || pinned.contains(&push_key(message)) {
+            continue;
+        }
+        keep[idx] = false;
+        total -= estimate_tokens(&message.content);
+    }
+    if keep.iter().all(|kept| *kept) {
+        return None;
+    }
+    Some(
+        cache
+            .iter()
+            .zip(keep)
+            .filter(|(_, kept)| *kept)
+            .map(|(message, _)| message.to_owned())
+            .collect(),
+    )
+}
@@ -1,12 +1,30 @@
 use espionox::{
     agents::Agent,
-    language_models::LanguageModel,
-    memory::{Memory, Message},
+    memory::{Message, MessageVector},
 };
 
-use super::{BackendError, BackendSender};
-use crate::logic::comms::FrontendRequest;
-use std::sync::Arc;
+use super::{
+    budget::trim_to_budget,
+    config::{AgentConfig, CachingConfig, RecallConfig},
+    database::{DatabaseStatus, SharedDatabaseSettings, SharedDatabaseStatus},
+    inspector::{compaction, inspect, push_key, MemoryCompaction},
+    long_term,
+    retry::RetryPolicy,
+    settings::SharedCompletionSettings,
+    watchdog::{StallKind, StallWatchdog},
+    BackendError, BackendSender,
+};
+use crate::logic::{
+    comms::FrontendRequest,
+    persistence::{
+        self,
+        chats::{agent_journal, load_agent, save_agent, AgentEntry, SavedAgent},
+        journal::Journal,
+        ltm_spool::{spool, SpooledWrite},
+        SavedMessage,
+    },
+};
+use std::{collections::HashSet, sync::Arc, time::Duration};
 use tokio::{
     sync::{mpsc, Mutex},
     task::JoinHandle,
@@ -16,57 +34,214 @@ use tokio::{
 pub struct ChatAgentThread {
     handle: Option<JoinHandle<()>>,
     pub name: String,
-    agent_construct: Option<AgentConstructor>,
+    pub config: AgentConfig,
+    /// The agent is only built once the thread knows whether long term memory is up
+    initial_memory: Option<MessageVector>,
     pub sender: Option<mpsc::Sender<ChatAgentMutation>>,
     outer_sender: Arc<BackendSender>,
+    settings: SharedCompletionSettings,
+    database: SharedDatabaseSettings,
+    database_status: SharedDatabaseStatus,
+    retry_override: Option<RetryPolicy>,
     // receiver: Option<mpsc::Receiver<String>>,
 }
 
 pub enum ChatAgentMutation {
     Prompt(String),
-    PushMessage(Message),
-}
-
-#[derive(Debug, Clone)]
-struct AgentConstructor {
-    memory: Memory,
-    model: LanguageModel,
-}
-
-impl From<Agent> for AgentConstructor {
-    fn from(value: Agent) -> Self {
-        let memory = value.memory;
-        let model = value.model;
-        Self { memory, model }
-    }
-}
-
-impl Into<Agent> for AgentConstructor {
-    fn into(self) -> Agent {
-        Agent {
-            memory: self.memory,
-            model: self.model,
-        }
-    }
+    PushMessages(Vec<Message>),
+    SetRetryPolicy(Option<RetryPolicy>),
+    SetRecall(RecallConfig),
+    SetPinned {
+        message: SavedMessage,
+        pinned: bool,
+    },
+    /// Answered with `AgentMemory`
+    Inspect,
+    /// Sends the last prompt again in place of its stalled exchange
+    RetryLastPrompt,
+    /// Replaces the cache with an imported conversation, which was prompted rather than
+    /// pushed
+    RestoreHistory(Vec<Message>),
+    /// Saves a message to the chat's long term thread, answered with `RememberedInLongTerm`
+    Remember(SavedMessage),
 }
 
 #[derive(Debug, Clone)]
 pub(super) struct ChatThreadVector(Vec<Arc<Mutex<ChatAgentThread>>>);
 
 impl ChatAgentThread {
-    pub fn new(name: &str, agent: Agent, outer_sender: Arc<BackendSender>) -> Self {
+    pub fn new(
+        name: &str,
+        config: AgentConfig,
+        memory: MessageVector,
+        outer_sender: Arc<BackendSender>,
+        settings: SharedCompletionSettings,
+        database: SharedDatabaseSettings,
+        database_status: SharedDatabaseStatus,
+    ) -> Self {
         let agent_thread = ChatAgentThread {
             handle: None,
             name: name.to_string(),
-            agent_construct: Some(agent.into()),
+            config,
+            initial_memory: Some(memory),
             sender: None,
             outer_sender,
+            settings,
+            database,
+            database_status,
+            retry_override: None,
         };
         agent_thread
     }
 
+    /// Waits out the first database check, so a chat that uses long term memory is never
+    /// built against a database that isn't there
+    async fn long_term_online(config: &AgentConfig, status: &SharedDatabaseStatus) -> bool {
+        if config.long_term_thread.is_none() {
+            return false;
+        }
+        loop {
+            match &*status.read().await {
+                DatabaseStatus::Unknown => {}
+                DatabaseStatus::Connected => return true,
+                DatabaseStatus::Disconnected(_) => return false,
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    }
+
+    fn build_agent(config: &AgentConfig, long_term_online: bool, memory: MessageVector) -> Agent {
+        match long_term_online {
+            true => config.build_agent(memory),
+            false => config.short_term_only().build_agent(memory),
+        }
+    }
+
+    async fn report_long_term(
+        chat_name: &str,
+        config: &AgentConfig,
+        online: bool,
+        sender: &BackendSender,
+    ) {
+        if config.long_term_thread.is_none() {
+            return;
+        }
+        tracing::info!("{} long term memory online: {}", chat_name, online);
+        sender
+            .send(FrontendRequest::LongTermMemoryStatus {
+                chat_name: chat_name.to_string(),
+                online,
+            })
+            .await
+            .unwrap();
+    }
+
+    /// Offline the agent is built without `save_to_lt`, so a summary espionox would have
+    /// saved to long term memory is kept in the spool instead. Nothing else the chat does
+    /// reaches long term memory on its own
+    fn spool_summary_if_offline(config: &AgentConfig, long_term_online: bool, summary: String) {
+        let saves_summaries = matches!(
+            config.caching,
+            CachingConfig::SummarizeAtLimit {
+                save_to_lt: true,
+                ..
+            }
+        );
+        if let (Some(thread), true, false) =
+            (&config.long_term_thread, saves_summaries, long_term_online)
+        {
+            spool(&SpooledWrite {
+                thread: thread.to_owned(),
+                message: SavedMessage {
+                    role: "system".to_string(),
+                    content: summary,
+                },
+            });
+        }
+    }
+
+    /// A failed completion might be the database going away before the monitor noticed,
+    /// in which case every chat is told and this one drops to short term memory
+    async fn database_went_down(
+        database: &SharedDatabaseSettings,
+        status: &SharedDatabaseStatus,
+        sender: &BackendSender,
+    ) -> bool {
+        let checked = database.check_connection().await;
+        if checked == DatabaseStatus::Connected {
+            return false;
+        }
+        *status.write().await = checked.clone();
+        sender
+            .send(FrontendRequest::DatabaseStatus(checked))
+            .await
+            .unwrap();
+        true
+    }
+
+    /// Lets the chat know when the caching mechanism summarized or forgot older messages
+    async fn report_compaction(
+        chat_name: &str,
+        before: &[SavedMessage],
+        agent: &Agent,
+        pushed: &HashSet<u64>,
+        sender: &BackendSender,
+    ) -> Option<MemoryCompaction> {
+        let after = persistence::saved_messages(agent.memory.cache());
+        let compaction = compaction(before, &after, pushed);
+        if let Some(compaction) = &compaction {
+            tracing::info!(
+                "{} messages dropped from {} memory",
+                compaction.removed,
+                chat_name
+            );
+            sender
+                .send(FrontendRequest::MemoryCompacted {
+                    chat_name: chat_name.to_string(),
+                    compaction: compaction.clone(),
+                })
+                .await
+                .unwrap();
+        }
+        compaction
+    }
+
+    /// Writes a full snapshot of the agent, after which its journal can be emptied
+    fn save(
+        chat_name: &str,
+        config: &AgentConfig,
+        retry_override: &Option<RetryPolicy>,
+        pushed: &HashSet<u64>,
+        agent: &Agent,
+        journal: &mut Journal,
+    ) {
+        let memory = persistence::saved_messages(agent.memory.cache());
+        // Pushes that have since been summarized away aren't worth keeping track of
+        let pushed = memory
+            .iter()
+            .map(push_key)
+            .filter(|key| pushed.contains(key))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let saved = SavedAgent {
+            name: chat_name.to_string(),
+            config: config.clone(),
+            memory,
+            retry_override: retry_override.clone(),
+            pushed,
+        };
+        match save_agent(&saved) {
+            Ok(_) => journal.truncate(),
+            Err(err) => tracing::warn!("Failed to save {} agent: {:?}", chat_name, err),
+        }
+    }
+
+    /// Aborts the thread so nothing it's in the middle of gets saved afterwards
     pub fn close(&mut self) {
-        self.handle = None;
+        if let Some(handle) = self.handle.take() {
+            handle.abort();
+        }
         self.sender = None;
     }
 
@@ -81,31 +256,261 @@ impl ChatAgentThread {
         tracing::info!("Set sender for {} agent thread", self.name);
         let outer_sender = Arc::clone(&self.outer_sender);
         let chat_name = self.name.to_string();
-        let agent_const = self.agent_construct.take();
+        let memory = self.initial_memory.take();
+        let settings = Arc::clone(&self.settings);
+        let database = Arc::clone(&self.database);
+        let database_status = Arc::clone(&self.database_status);
+        let mut retry_override = self.retry_override.clone();
+        let mut config = self.config.clone();
         let handle = tokio::spawn(async move {
-            let mut agent = agent_const.unwrap().into();
+            let mut long_term_online = Self::long_term_online(&config, &database_status).await;
+            let mut agent = Self::build_agent(&config, long_term_online, memory.unwrap());
+            Self::report_long_term(&chat_name, &config, long_term_online, &outer_sender).await;
+            let mut pushed: HashSet<u64> = load_agent(&chat_name)
+                .map(|saved| saved.pushed.into_iter().collect())
+                .unwrap_or_default();
+            let mut journal = agent_journal(&chat_name);
+            Self::save(
+                &chat_name,
+                &config,
+                &retry_override,
+                &pushed,
+                &agent,
+                &mut journal,
+            );
             loop {
                 tracing::info!("Listening on {} agent thread...", &chat_name);
-                match rx.try_recv() {
+                let online = Self::long_term_online(&config, &database_status).await;
+                if config.long_term_thread.is_some() && online != long_term_online {
+                    long_term_online = online;
+                    agent = Self::build_agent(&config, online, agent.memory.cache().clone());
+                    Self::report_long_term(&chat_name, &config, online, &outer_sender).await;
+                }
+                let received = match rx.try_recv() {
+                    Ok(ChatAgentMutation::RetryLastPrompt) => {
+                        let memory = persistence::saved_messages(agent.memory.cache());
+                        let idx = match memory.iter().rposition(|m| m.role == "user") {
+                            Some(idx) => idx,
+                            None => {
+                                tracing::warn!("No prompt to retry on {} thread", chat_name);
+                                continue;
+                            }
+                        };
+                        tracing::info!("Rolling back the last exchange on {} thread", chat_name);
+                        agent = Self::build_agent(
+                            &config,
+                            long_term_online,
+                            persistence::message_vector(&memory[..idx]),
+                        );
+                        Self::save(
+                            &chat_name,
+                            &config,
+                            &retry_override,
+                            &pushed,
+                            &agent,
+                            &mut journal,
+                        );
+                        Ok(ChatAgentMutation::Prompt(memory[idx].content.to_owned()))
+                    }
+                    received => received,
+                };
+                match received {
                     Ok(mutation) => match mutation {
                         ChatAgentMutation::Prompt(prompt) => {
                             tracing::info!("Prompt received on {} agent thread...", chat_name);
-                            Self::handle_completion_stream(
+                            let before = persistence::saved_messages(agent.memory.cache());
+                            if let Some(trimmed) = config.token_budget().and_then(|max_tokens| {
+                                trim_to_budget(&before, &prompt, max_tokens, &config.pinned_keys())
+                            }) {
+                                agent = Self::build_agent(
+                                    &config,
+                                    long_term_online,
+                                    persistence::message_vector(&trimmed),
+                                );
+                            }
+                            let current_settings = settings.read().await.clone();
+                            let policy = match &retry_override {
+                                Some(policy) => policy.clone(),
+                                None => current_settings.retry,
+                            };
+                            // The prompt is in memory as soon as the stream starts, so it's
+                            // journaled before that in case we never get to the snapshot
+                            journal.append(&AgentEntry::Message {
+                                message: SavedMessage {
+                                    role: "user".to_string(),
+                                    content: prompt.to_owned(),
+                                },
+                            });
+                            if let Err(err) = Self::handle_completion_with_retries(
                                 chat_name.clone(),
                                 prompt,
                                 &mut agent,
                                 Arc::clone(&outer_sender),
+                                &policy,
+                                current_settings.watchdog,
+                                |cache| Self::build_agent(&config, long_term_online, cache),
+                            )
+                            .await
+                            {
+                                tracing::warn!(
+                                    "Completion failed on {} thread: {}",
+                                    chat_name,
+                                    err
+                                );
+                                if long_term_online
+                                    && Self::database_went_down(
+                                        &database,
+                                        &database_status,
+                                        &outer_sender,
+                                    )
+                                    .await
+                                {
+                                    long_term_online = false;
+                                    agent = Self::build_agent(
+                                        &config,
+                                        false,
+                                        agent.memory.cache().clone(),
+                                    );
+                                    Self::report_long_term(
+                                        &chat_name,
+                                        &config,
+                                        false,
+                                        &outer_sender,
+                                    )
+                                    .await;
+                                }
+                            }
+                            if let Some(summary) = Self::report_compaction(
+                                &chat_name,
+                                &before,
+                                &agent,
+                                &pushed,
+                                &outer_sender,
                             )
                             .await
-                            .unwrap();
+                            .and_then(|compaction| compaction.summary)
+                            {
+                                Self::spool_summary_if_offline(&config, long_term_online, summary);
+                            }
+                            Self::save(
+                                &chat_name,
+                                &config,
+                                &retry_override,
+                                &pushed,
+                                &agent,
+                                &mut journal,
+                            );
+                        }
+                        ChatAgentMutation::PushMessages(messages) => {
+                            tracing::info!("Received {} messages on agent thread", messages.len());
+                            for message in messages.into_iter() {
+                                let saved_message = SavedMessage::from(&message);
+                                pushed.insert(push_key(&saved_message));
+                                journal.append(&AgentEntry::Pushed {
+                                    key: push_key(&saved_message),
+                                    message: saved_message,
+                                });
+                                agent.memory.force_push_message_to_cache(message);
+                            }
+                        }
+                        ChatAgentMutation::RestoreHistory(history) => {
+                            tracing::info!(
+                                "Restoring {} messages on {} thread",
+                                history.len(),
+                                chat_name
+                            );
+                            agent = Self::build_agent(&config, long_term_online, history.into());
+                            Self::save(
+                                &chat_name,
+                                &config,
+                                &retry_override,
+                                &pushed,
+                                &agent,
+                                &mut journal,
+                            );
+                        }
+                        ChatAgentMutation::SetRetryPolicy(policy) => {
+                            tracing::info!("Setting retry policy on {} thread", chat_name);
+                            journal.append(&AgentEntry::RetryPolicy {
+                                policy: policy.clone(),
+                            });
+                            retry_override = policy;
                         }
-                        ChatAgentMutation::PushMessage(message) => {
-                            tracing::info!("Received message on agent thread");
-                            agent.memory.force_push_message_to_cache(message);
+                        ChatAgentMutation::SetRecall(recall) => {
+                            tracing::info!("Setting recall mode on {} thread", chat_name);
+                            let before = config.clone();
+                            config.recall = recall;
+                            agent = Self::build_agent(
+                                &config,
+                                long_term_online,
+                                agent.memory.cache().clone(),
+                            );
+                            for entry in AgentEntry::config_changes(&before, &config) {
+                                journal.append(&entry);
+                            }
+                        }
+                        ChatAgentMutation::SetPinned { message, pinned } => {
+                            tracing::info!("Setting a pin on {} thread", chat_name);
+                            let before = config.clone();
+                            config.set_pinned(message, pinned);
+                            for entry in AgentEntry::config_changes(&before, &config) {
+                                journal.append(&entry);
+                            }
+                        }
+                        ChatAgentMutation::Inspect => {
+                            outer_sender
+                                .send(FrontendRequest::AgentMemory {
+                                    chat_name: chat_name.to_owned(),
+                                    messages: inspect(
+                                        agent.memory.cache(),
+                                        &pushed,
+                                        &config.pinned_keys(),
+                                    ),
+                                    pins_apply: config.token_budget().is_some(),
+                                })
+                                .await
+                                .unwrap();
+                        }
+                        ChatAgentMutation::RetryLastPrompt => {
+                            unreachable!("turned into a Prompt above")
+                        }
+                        ChatAgentMutation::Remember(message) => {
+                            tracing::info!("Remembering a message on {} thread", chat_name);
+                            let result = match &config.long_term_thread {
+                                None => Err("This chat has no long term thread".to_string()),
+                                Some(thread) if !long_term_online => {
+                                    spool(&SpooledWrite {
+                                        thread: thread.to_owned(),
+                                        message,
+                                    });
+                                    Ok(format!("Queued for {} until the database is back", thread))
+                                }
+                                Some(thread) => long_term::save_to(&agent.memory, &message)
+                                    .await
+                                    .map(|_| format!("Saved to {}", thread))
+                                    .map_err(|err| err.to_string()),
+                            };
+                            outer_sender
+                                .send(FrontendRequest::RememberedInLongTerm {
+                                    chat_name: chat_name.to_owned(),
+                                    result,
+                                })
+                                .await
+                                .unwrap();
                         }
                     },
                     Err(err) => match err {
                         tokio::sync::mpsc::error::TryRecvError::Empty => {
+                            if journal.needs_compaction() {
+                                Self::save(
+                                    &chat_name,
+                                    &config,
+                                    &retry_override,
+                                    &pushed,
+                                    &agent,
+                                    &mut journal,
+                                );
+                            }
                             tokio::time::sleep(std::time::Duration::from_secs(2)).await;
                         }
                         tokio::sync::mpsc::error::TryRecvError::Disconnected => {
@@ -121,18 +526,114 @@ impl ChatAgentThread {
         Ok(())
     }
 
+    pub fn set_retry_override(&mut self, policy: Option<RetryPolicy>) {
+        self.retry_override = policy;
+    }
+
+    async fn handle_completion_with_retries(
+        chat_name: String,
+        prompt: String,
+        agent: &mut Agent,
+        sender: Arc<BackendSender>,
+        policy: &RetryPolicy,
+        watchdog: StallWatchdog,
+        rebuild: impl Fn(MessageVector) -> Agent,
+    ) -> Result<(), BackendError> {
+        // A failed attempt may have left the prompt in memory, each retry starts from here
+        // so it's only ever there once
+        let before_attempt = agent.memory.cache().clone();
+        let mut attempt = 1;
+        loop {
+            let result = Self::handle_completion_stream(
+                chat_name.clone(),
+                prompt.clone(),
+                agent,
+                Arc::clone(&sender),
+                watchdog,
+            )
+            .await;
+            let err = match result {
+                Ok(()) => return Ok(()),
+                Err(err) => err,
+            };
+            match policy.retry_delay(&err, attempt) {
+                Some(delay) => {
+                    tracing::warn!(
+                        "Attempt {}/{} failed on {} thread, retrying in {:?}: {}",
+                        attempt,
+                        policy.max_attempts,
+                        chat_name,
+                        delay,
+                        err
+                    );
+                    attempt += 1;
+                    *agent = rebuild(before_attempt.clone());
+                    sender
+                        .send(FrontendRequest::RetryingCompletion {
+                            chat_name: chat_name.to_owned(),
+                            attempt,
+                            max_attempts: policy.max_attempts,
+                            delay,
+                        })
+                        .await
+                        .unwrap();
+                    tokio::time::sleep(delay).await;
+                }
+                None => {
+                    sender
+                        .send(FrontendRequest::CompletionFailed {
+                            chat_name: chat_name.to_owned(),
+                            error: err.to_string(),
+                        })
+                        .await
+                        .unwrap();
+                    return Err(err);
+                }
+            }
+        }
+    }
+
     async fn handle_completion_stream(
         chat_name: String,
         prompt: String,
         agent: &mut Agent,
         sender: Arc<BackendSender>,
+        watchdog: StallWatchdog,
     ) -> Result<(), BackendError> {
         let mut stream_receiver = agent
             .stream_prompt(prompt)
             .await
             .map_err(|err| BackendError::Unexpected(err.into()))?;
         let mut full_message = vec![];
-        while let Ok(Some(token_response)) = stream_receiver.receive().await {
+        let mut stall = None;
+        loop {
+            let received = match watchdog.timeout_for(full_message.len()) {
+                Some(timeout) => {
+                    match tokio::time::timeout(timeout, stream_receiver.receive()).await {
+                        Ok(received) => received,
+                        Err(_) => {
+                            stall = Some((
+                                StallKind::from_tokens_received(full_message.len()),
+                                timeout,
+                            ));
+                            break;
+                        }
+                    }
+                }
+                None => stream_receiver.receive().await,
+            };
+            let token_response = match received {
+                Ok(Some(token_response)) => token_response,
+                Ok(None) => break,
+                // Nothing has reached the frontend yet, so the whole exchange can be retried
+                Err(err) if full_message.is_empty() => {
+                    return Err(BackendError::Unexpected(err.into()));
+                }
+                Err(err) => {
+                    tracing::warn!("Stream for {} ended with error: {:?}", chat_name, err);
+                    break;
+                }
+            };
             tracing::info!("Sending Token: {}", token_response);
             let token = token_response.to_owned();
             let chat_name = chat_name.to_owned();
@@ -142,6 +643,28 @@ impl ChatAgentThread {
                 .unwrap();
             full_message.push(token_response.to_owned());
         }
+        // Dropping the receiver aborts the stalled stream
+        drop(stream_receiver);
+
+        if let Some((kind, waited)) = stall {
+            tracing::warn!("{} on {} thread after {:?}", kind, chat_name, waited);
+            if !full_message.is_empty() {
+                agent
+                    .memory
+                    .push_to_message_cache(Some("assistant"), full_message.join(""))
+                    .await;
+            }
+            sender
+                .send(FrontendRequest::StreamStalled {
+                    chat_name,
+                    kind,
+                    waited,
+                })
+                .await
+                .unwrap();
+            return Ok(());
+        }
+
         agent
             .memory
             .push_to_message_cache(Some("assistant"), full_message.join(""))
@@ -190,6 +713,37 @@ impl ChatThreadVector {
             }
         });
     }
+    /// Aborting only takes effect at a thread's next await, so this waits for every one
+    /// to actually stop. Nothing they were in the middle of can be written afterwards
+    pub async fn close_all(&mut self) {
+        let handles: Vec<JoinHandle<()>> = self
+            .0
+            .iter()
+            .filter_map(|thread_mutex| {
+                let mut thread = thread_mutex.try_lock().unwrap();
+                thread.sender = None;
+                thread.handle.take()
+            })
+            .collect();
+        self.0.clear();
+        for handle in handles {
+            handle.abort();
+            if let Err(err) = handle.await {
+                if !err.is_cancelled() {
+                    tracing::warn!("Chat thread panicked while closing: {:?}", err);
+                }
+            }
+        }
+    }
+    pub fn configs(&self) -> Vec<(String, AgentConfig)> {
+        self.0
+            .iter()
+            .map(|thread_mutex| {
+                let thread = thread_mutex.try_lock().unwrap();
+                (thread.name.to_string(), thread.config.clone())
+            })
+            .collect()
+    }
     pub fn get_by_name(&self, name: &str) -> Option<tokio::sync::MutexGuard<'_, ChatAgentThread>> {
         self.0
             .iter()
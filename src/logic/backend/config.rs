@@ -0,0 +1,177 @@
+use super::inspector::push_key;
+use crate::logic::persistence::SavedMessage;
+use espionox::{
+    agents::Agent,
+    language_models::LanguageModel,
+    memory::{CachingMechanism, Memory, MessageVector, RecallMode},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+pub const DEFAULT_TOKEN_BUDGET: usize = 4000;
+
+/// Everything needed to rebuild a chat's agent, minus what's in its memory
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct AgentConfig {
+    pub caching: CachingConfig,
+    pub recall: RecallConfig,
+    pub long_term_thread: Option<String>,
+    /// Kept in memory when a token budget forgets older messages. espionox summarizes
+    /// without asking, so `SummarizeAtLimit` treats pins like any other message
+    #[serde(default)]
+    pub pinned: Vec<SavedMessage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum CachingConfig {
+    Forgetful,
+    SummarizeAtLimit {
+        limit: usize,
+        save_to_lt: bool,
+    },
+    /// Forgets the oldest messages that aren't pinned once the next prompt would take the
+    /// context over `max_tokens`. Enforced by the chat thread with `trim_to_budget`, espionox
+    /// only counts messages and runs the agent as `Forgetful`
+    TokenBudget {
+        max_tokens: usize,
+    },
+}
+
+/// Mirrors espionox's `RecallMode` at the pinned rev (d5cd185), see `RECALL_MODES`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecallConfig {
+    Manual,
+    Auto,
+}
+
+/// espionox doesn't know about token budgets, an agent running one comes back as
+/// `Forgetful` and the saved config should be preferred where there is one
+impl From<&CachingMechanism> for CachingConfig {
+    fn from(value: &CachingMechanism) -> Self {
+        match value {
+            CachingMechanism::Forgetful => Self::Forgetful,
+            CachingMechanism::SummarizeAtLimit { limit, save_to_lt } => Self::SummarizeAtLimit {
+                limit: *limit,
+                save_to_lt: *save_to_lt,
+            },
+        }
+    }
+}
+
+impl From<&CachingConfig> for CachingMechanism {
+    fn from(value: &CachingConfig) -> Self {
+        match value {
+            CachingConfig::Forgetful => Self::Forgetful,
+            CachingConfig::SummarizeAtLimit { limit, save_to_lt } => Self::SummarizeAtLimit {
+                limit: *limit,
+                save_to_lt: *save_to_lt,
+            },
+            CachingConfig::TokenBudget { .. } => Self::Forgetful,
+        }
+    }
+}
+
+impl Default for CachingConfig {
+    fn default() -> Self {
+        Self::from(&CachingMechanism::default())
+    }
+}
+
+impl CachingConfig {
+    pub fn default_summary_at_limit() -> Self {
+        Self::from(&CachingMechanism::default_summary_at_limit())
+    }
+
+    pub fn default_token_budget() -> Self {
+        Self::TokenBudget {
+            max_tokens: DEFAULT_TOKEN_BUDGET,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Forgetful => "Forgetful",
+            Self::SummarizeAtLimit { .. } => "SummarizeAtLimit",
+            Self::TokenBudget { .. } => "TokenBudget",
+        }
+    }
+}
+
+impl From<&RecallMode> for RecallConfig {
+    fn from(value: &RecallMode) -> Self {
+        match value {
+            RecallMode::Manual => Self::Manual,
+            RecallMode::Auto => Self::Auto,
+        }
+    }
+}
+
+impl From<&RecallConfig> for RecallMode {
+    fn from(value: &RecallConfig) -> Self {
+        match value {
+            RecallConfig::Manual => Self::Manual,
+            RecallConfig::Auto => Self::Auto,
+        }
+    }
+}
+
+impl Default for RecallConfig {
+    fn default() -> Self {
+        Self::from(&RecallMode::default())
+    }
+}
+
+impl AgentConfig {
+    pub fn token_budget(&self) -> Option<usize> {
+        match self.caching {
+            CachingConfig::TokenBudget { max_tokens } => Some(max_tokens),
+            _ => None,
+        }
+    }
+
+    pub fn pinned_keys(&self) -> HashSet<u64> {
+        self.pinned.iter().map(push_key).collect()
+    }
+
+    pub fn is_pinned(&self, message: &SavedMessage) -> bool {
+        self.pinned.iter().any(|pin| pin.content == message.content)
+    }
+
+    pub fn set_pinned(&mut self, message: SavedMessage, pinned: bool) {
+        self.pinned.retain(|pin| pin.content != message.content);
+        if pinned {
+            self.pinned.push(message);
+        }
+    }
+
+    /// The same agent without anything that needs the long term memory database
+    pub fn short_term_only(&self) -> Self {
+        let caching = match &self.caching {
+            CachingConfig::SummarizeAtLimit { limit, .. } => CachingConfig::SummarizeAtLimit {
+                limit: *limit,
+                save_to_lt: false,
+            },
+            other => other.clone(),
+        };
+        Self {
+            caching,
+            long_term_thread: None,
+            ..self.clone()
+        }
+    }
+
+    /// `memory` seeds the agent's cache, for a new chat that's just its init prompt
+    pub fn build_agent(&self, memory: MessageVector) -> Agent {
+        let mut builder = Memory::build()
+            .caching_mechanism(CachingMechanism::from(&self.caching))
+            .recall(RecallMode::from(&self.recall))
+            .init_prompt(memory);
+        if let Some(thread_name) = &self.long_term_thread {
+            builder = builder.long_term_thread(thread_name);
+        }
+        Agent {
+            memory: builder.finished(),
+            model: LanguageModel::default_gpt(),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    postgres::{PgConnectOptions, PgConnection},
+    Connection,
+};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::RwLock;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connection to the Postgres database long term memory is kept in. espionox reads these
+/// from the `DB_*` environment variables, the same ones `docker-compose.yaml` uses
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseSettings {
+    pub host: String,
+    pub port: u16,
+    pub user: String,
+    pub password: String,
+    pub name: String,
+}
+
+/// The database espionox was pointed at when the app started, read from the same `DB_*`
+/// variables it connects with. espionox only reads the
+/// `DB_*` variables, which can't safely change while threads are running, so settings
+/// saved later wait for a restart rather than leaving the app checking one database and
+/// writing to another
+pub type SharedDatabaseSettings = Arc<DatabaseSettings>;
+
+/// Last known status of the saved settings, chat threads fall back to short term memory
+/// while it isn't `Connected`
+pub type SharedDatabaseStatus = Arc<RwLock<DatabaseStatus>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DatabaseStatus {
+    Unknown,
+    Connected,
+    Disconnected(String),
+}
+
+/// Commands get logged, so the password is left out
+impl std::fmt::Debug for DatabaseSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DatabaseSettings")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("user", &self.user)
+            .field("password", &"…")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 5432,
+            user: "postgres".to_string(),
+            password: String::new(),
+            name: "espionox".to_string(),
+        }
+    }
+}
+
+impl DatabaseSettings {
+    /// Whatever is already in the environment, anything missing keeps its default
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let var = |key: &str, default: String| std::env::var(key).unwrap_or(default);
+        Self {
+            host: var("DB_HOST", default.host),
+            port: std::env::var("DB_PORT")
+                .ok()
+                .and_then(|port| port.parse().ok())
+                .unwrap_or(default.port),
+            user: var("DB_USER", default.user),
+            password: var("DB_PASSWORD", default.password),
+            name: var("DB_NAME", default.name),
+        }
+    }
+
+    /// Points espionox at this database. `set_var` races with anything reading the
+    /// environment on another thread, so this is only called from `main` before any are
+    /// started. Everything in the app itself is handed its settings instead
+    pub fn apply_to_env(&self) {
+        std::env::set_var("DB_HOST", &self.host);
+        std::env::set_var("DB_PORT", self.port.to_string());
+        std::env::set_var("DB_USER", &self.user);
+        std::env::set_var("DB_PASSWORD", &self.password);
+        std::env::set_var("DB_NAME", &self.name);
+    }
+
+    fn connect_options(&self) -> PgConnectOptions {
+        PgConnectOptions::new()
+            .host(&self.host)
+            .port(self.port)
+            .username(&self.user)
+            .password(&self.password)
+            .database(&self.name)
+    }
+
+    /// Gives up after a few seconds rather than hanging on an unreachable host
+    pub async fn connect(&self) -> anyhow::Result<PgConnection> {
+        let connect = PgConnection::connect_with(&self.connect_options());
+        match tokio::time::timeout(CONNECT_TIMEOUT, connect).await {
+            Ok(connection) => Ok(connection?),
+            Err(_) => Err(anyhow::anyhow!("Timed out connecting")),
+        }
+    }
+
+    /// Opens and closes a connection
+    pub async fn check_connection(&self) -> DatabaseStatus {
+        match self.connect().await {
+            Ok(connection) => {
+                if let Err(err) = connection.close().await {
+                    tracing::warn!("Couldn't close database connection: {:?}", err);
+                }
+                DatabaseStatus::Connected
+            }
+            Err(err) => DatabaseStatus::Disconnected(err.to_string()),
+        }
+    }
+}
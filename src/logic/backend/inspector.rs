@@ -0,0 +1,137 @@
+use super::semantic::fnv1a;
+use crate::logic::persistence::SavedMessage;
+use espionox::memory::{MessageRole, MessageVector};
+use std::collections::{HashMap, HashSet};
+
+/// Where a message in the agent's memory came from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MessageOrigin {
+    InitPrompt,
+    /// Files, directories, accepted context and imported history
+    Push,
+    /// Written by the caching mechanism in place of older messages
+    Summary,
+    Conversation,
+}
+
+impl std::fmt::Display for MessageOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let origin = match self {
+            Self::InitPrompt => "init prompt",
+            Self::Push => "push",
+            Self::Summary => "summary",
+            Self::Conversation => "conversation",
+        };
+        write!(f, "{}", origin)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InspectedMessage {
+    pub role: String,
+    pub origin: MessageOrigin,
+    pub content: String,
+    pub chars: usize,
+    /// Estimated, the model's tokenizer isn't available here
+    pub tokens: usize,
+    pub pinned: bool,
+}
+
+/// Roughly four characters a token for English text, good enough to compare messages
+pub fn estimate_tokens(text: &str) -> usize {
+    (text.chars().count() + 3) / 4
+}
+
+/// Identifies a pushed message by its content, which is all that survives in the cache
+pub fn push_key(message: &SavedMessage) -> u64 {
+    fnv1a(&message.content)
+}
+
+/// The agent's cache as it will be sent with the next prompt
+pub fn inspect(
+    cache: &MessageVector,
+    pushed: &HashSet<u64>,
+    pinned: &HashSet<u64>,
+) -> Vec<InspectedMessage> {
+    cache
+        .as_ref()
+        .iter()
+        .enumerate()
+        .map(|(idx, message)| {
+            let saved = SavedMessage::from(message);
+            let origin = match message.role() {
+                _ if pushed.contains(&push_key(&saved)) => MessageOrigin::Push,
+                MessageRole::User | MessageRole::Assistant => MessageOrigin::Conversation,
+                _ if idx == 0 => MessageOrigin::InitPrompt,
+                _ => MessageOrigin::Summary,
+            };
+            InspectedMessage {
+                origin,
+                chars: saved.content.chars().count(),
+                tokens: estimate_tokens(&saved.content),
+                pinned: pinned.contains(&push_key(&saved)),
+                role: saved.role,
+                content: saved.content,
+            }
+        })
+        .collect()
+}
+
+/// Older messages the caching mechanism dropped from memory
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemoryCompaction {
+    pub removed: usize,
+    /// `None` when they were forgotten outright
+    pub summary: Option<String>,
+}
+
+/// Compares the cache before and after a change to see whether the caching mechanism
+/// kicked in. Any system message that's new and wasn't pushed is taken as the summary
+pub fn compaction(
+    before: &[SavedMessage],
+    after: &[SavedMessage],
+    pushed: &HashSet<u64>,
+) -> Option<MemoryCompaction> {
+    let mut remaining: HashMap<&str, usize> = HashMap::new();
+    for message in after.iter() {
+        *remaining.entry(&message.content).or_default() += 1;
+    }
+    let removed = before
+        .iter()
+        .filter(
+            |message| match remaining.get_mut(message.content.as_str()) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            },
+        )
+        .count();
+    if removed == 0 {
+        return None;
+    }
+    let mut previous: HashMap<&str, usize> = HashMap::new();
+    for message in before.iter() {
+        *previous.entry(&message.content).or_default() += 1;
+    }
+    let summaries: Vec<&str> = after
+        .iter()
+        .filter(|message| match previous.get_mut(message.content.as_str()) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        })
+        .filter(|message| message.role == "system" && !pushed.contains(&push_key(message)))
+        .map(|message| message.content.as_str())
+        .collect();
+    Some(MemoryCompaction {
+        removed,
+        summary: match summaries.is_empty() {
+            true => None,
+            false => Some(summaries.join("\n\n")),
+        },
+    })
+}
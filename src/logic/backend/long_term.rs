@@ -0,0 +1,277 @@
+use super::database::DatabaseSettings;
+use crate::logic::persistence::{
+    ltm_spool::{remove_replayed, spooled},
+    message_vector, SavedMessage,
+};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use espionox::memory::Memory;
+use serde_json::Value;
+use sqlx::postgres::PgConnection;
+
+/// Where espionox keeps long term memory, as created by its migrations at the rev pinned in
+/// `Cargo.lock` (d5cd185). They aren't vendored with the crate, so every read or delete
+/// first goes through `check_schema` and fails with what's missing instead of a SQL error
+const THREADS_TABLE: &str = "threads";
+const THREAD_NAME: &str = "name";
+const MESSAGES_TABLE: &str = "messages";
+const MESSAGE_THREAD: &str = "thread_name";
+const MESSAGE_ID: &str = "id";
+const MESSAGE_CONTENT: &str = "content";
+const MESSAGE_ROLE: &str = "role";
+
+const EXPECTED_COLUMNS: [(&str, &str); 5] = [
+    (THREADS_TABLE, THREAD_NAME),
+    (MESSAGES_TABLE, MESSAGE_THREAD),
+    (MESSAGES_TABLE, MESSAGE_ID),
+    (MESSAGES_TABLE, MESSAGE_CONTENT),
+    (MESSAGES_TABLE, MESSAGE_ROLE),
+];
+
+/// A message or summary stored in a long term thread
+#[derive(Debug, Clone, PartialEq)]
+pub struct LtmEntry {
+    pub id: String,
+    pub role: String,
+    pub content: String,
+    pub created_at: Option<DateTime<Utc>>,
+    /// `false` for a row without the fields espionox writes, `content` is then the raw row
+    pub readable: bool,
+}
+
+impl LtmEntry {
+    /// Rows are read as JSON so columns espionox adds or renames don't break the browser.
+    /// `timestamp_column` is whichever column of `messages` holds a timestamp
+    fn from_row(row: &str, timestamp_column: Option<&str>) -> Self {
+        let parsed: Option<Value> = serde_json::from_str(row).ok();
+        let field = |key: &str| {
+            parsed
+                .as_ref()
+                .and_then(|row| row.get(key))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        };
+        let id = match parsed.as_ref().and_then(|row| row.get(MESSAGE_ID)) {
+            Some(Value::String(id)) => id.to_owned(),
+            Some(other) => other.to_string(),
+            None => String::new(),
+        };
+        let created_at = timestamp_column
+            .and_then(|column| field(column))
+            .and_then(|timestamp| parse_timestamp(&timestamp));
+        match field(MESSAGE_CONTENT) {
+            Some(content) if !id.is_empty() => Self {
+                id,
+                role: field(MESSAGE_ROLE).unwrap_or_default(),
+                content,
+                created_at,
+                readable: true,
+            },
+            _ => Self {
+                id,
+                role: String::new(),
+                content: row.to_string(),
+                created_at,
+                readable: false,
+            },
+        }
+    }
+
+    /// Summaries made by `SummarizeAtLimit` are saved as system messages
+    pub fn is_summary(&self) -> bool {
+        self.readable && self.role == "system"
+    }
+}
+
+/// Postgres renders `timestamptz` as RFC 3339 but plain `timestamp` without an offset
+fn parse_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    if let Ok(parsed) = DateTime::parse_from_rfc3339(timestamp) {
+        return Some(parsed.with_timezone(&Utc));
+    }
+    NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .map(|naive| DateTime::from_naive_utc_and_offset(naive, Utc))
+}
+
+/// Connects and makes sure every column the browser relies on is there
+async fn connect_checked(settings: &DatabaseSettings) -> anyhow::Result<PgConnection> {
+    let mut connection = settings.connect().await?;
+    check_schema(&mut connection).await?;
+    Ok(connection)
+}
+
+async fn check_schema(connection: &mut PgConnection) -> anyhow::Result<()> {
+    let present = sqlx::query_as::<_, (String, String)>(
+        "SELECT table_name::text, column_name::text FROM information_schema.columns \
+         WHERE table_schema = current_schema()",
+    )
+    .fetch_all(&mut *connection)
+    .await?;
+    let missing: Vec<String> = EXPECTED_COLUMNS
+        .iter()
+        .filter(|(table, column)| !present.iter().any(|(t, c)| t == table && c == column))
+        .map(|(table, column)| format!("{}.{}", table, column))
+        .collect();
+    match missing.is_empty() {
+        true => Ok(()),
+        false => Err(anyhow::anyhow!(
+            "The database doesn't have the long term memory schema this version of espionox \
+             uses, missing {}",
+            missing.join(", ")
+        )),
+    }
+}
+
+/// Asks the database rather than guessing a name, `None` if espionox's schema doesn't
+/// record when messages were saved
+async fn timestamp_column(connection: &mut PgConnection) -> anyhow::Result<Option<String>> {
+    let column = sqlx::query_scalar::<_, String>(
+        "SELECT column_name::text FROM information_schema.columns \
+         WHERE table_schema = current_schema() AND table_name = $1 \
+         AND data_type IN ('timestamp with time zone', 'timestamp without time zone') \
+         ORDER BY ordinal_position LIMIT 1",
+    )
+    .bind(MESSAGES_TABLE)
+    .fetch_optional(&mut *connection)
+    .await?;
+    Ok(column)
+}
+
+pub async fn list_threads(settings: &DatabaseSettings) -> anyhow::Result<Vec<String>> {
+    let mut connection = connect_checked(settings).await?;
+    threads_on(&mut connection).await
+}
+
+async fn threads_on(connection: &mut PgConnection) -> anyhow::Result<Vec<String>> {
+    let query = format!(
+        "SELECT {} FROM {} UNION SELECT {} FROM {} ORDER BY 1",
+        THREAD_NAME, THREADS_TABLE, MESSAGE_THREAD, MESSAGES_TABLE
+    );
+    let threads = sqlx::query_scalar::<_, String>(&query)
+        .fetch_all(connection)
+        .await?;
+    Ok(threads)
+}
+
+/// Oldest first when the schema has a timestamp, otherwise in the order Postgres returns
+/// them. Rows that can't be read are kept, marked as such
+pub async fn load_thread(
+    settings: &DatabaseSettings,
+    thread: &str,
+) -> anyhow::Result<Vec<LtmEntry>> {
+    let mut connection = connect_checked(settings).await?;
+    let timestamp_column = timestamp_column(&mut connection).await?;
+    thread_on(&mut connection, thread, timestamp_column.as_deref()).await
+}
+
+/// Every thread and its entries over a single connection, a thread that can't be read is
+/// left out with a warning
+pub async fn load_threads(
+    settings: &DatabaseSettings,
+) -> anyhow::Result<Vec<(String, Vec<LtmEntry>)>> {
+    let mut connection = connect_checked(settings).await?;
+    let timestamp_column = timestamp_column(&mut connection).await?;
+    let mut threads = vec![];
+    for thread in threads_on(&mut connection).await?.into_iter() {
+        match thread_on(&mut connection, &thread, timestamp_column.as_deref()).await {
+            Ok(entries) => threads.push((thread, entries)),
+            Err(err) => tracing::warn!("Couldn't load {} thread: {:?}", thread, err),
+        }
+    }
+    Ok(threads)
+}
+
+async fn thread_on(
+    connection: &mut PgConnection,
+    thread: &str,
+    timestamp_column: Option<&str>,
+) -> anyhow::Result<Vec<LtmEntry>> {
+    let query = format!(
+        "SELECT to_jsonb(m)::text FROM {} m WHERE m.{} = $1",
+        MESSAGES_TABLE, MESSAGE_THREAD
+    );
+    let rows = sqlx::query_scalar::<_, String>(&query)
+        .bind(thread)
+        .fetch_all(connection)
+        .await?;
+    let mut entries: Vec<LtmEntry> = rows
+        .iter()
+        .map(|row| LtmEntry::from_row(row, timestamp_column))
+        .collect();
+    let unreadable = entries.iter().filter(|entry| !entry.readable).count();
+    if unreadable > 0 {
+        tracing::warn!("{} unreadable rows in {} thread", unreadable, thread);
+    }
+    entries.sort_by_key(|entry| entry.created_at);
+    Ok(entries)
+}
+
+pub async fn delete_entry(settings: &DatabaseSettings, id: &str) -> anyhow::Result<()> {
+    let mut connection = connect_checked(settings).await?;
+    let query = format!(
+        "DELETE FROM {} WHERE {}::text = $1",
+        MESSAGES_TABLE, MESSAGE_ID
+    );
+    let deleted = sqlx::query(&query)
+        .bind(id)
+        .execute(&mut connection)
+        .await?;
+    if deleted.rows_affected() == 0 {
+        return Err(anyhow::anyhow!("No entry with id {}", id));
+    }
+    Ok(())
+}
+
+/// Writes whatever was spooled while the database was down, oldest first. Only called once
+/// the monitor sees the `DB_*` database come back, the same one `save_entry` writes to. A
+/// write only leaves the spool once it's in the database, so stopping at a failure, or
+/// crashing, leaves the rest for next time
+pub async fn replay_spool() -> anyhow::Result<usize> {
+    let writes = spooled();
+    let mut replayed = 0;
+    let mut result = Ok(());
+    for write in writes.iter() {
+        if let Err(err) = save_entry(&write.thread, &write.message).await {
+            result = Err(err);
+            break;
+        }
+        replayed += 1;
+    }
+    remove_replayed(replayed)?;
+    result.map(|_| replayed)
+}
+
+/// Goes through the memory's own long term thread, the same way its summaries are saved,
+/// so the row matches whatever schema the database has
+pub async fn save_to(memory: &Memory, message: &SavedMessage) -> anyhow::Result<()> {
+    memory
+        .long_term
+        .save_messages_to_database(&message_vector(std::slice::from_ref(message)))
+        .await?;
+    Ok(())
+}
+
+/// For spooled writes, whose chat may be gone or busy by the time they're replayed. Like
+/// the chats, it connects with the `DB_*` variables
+async fn save_entry(thread: &str, message: &SavedMessage) -> anyhow::Result<()> {
+    let memory = Memory::build().long_term_thread(thread).finished();
+    save_to(&memory, message).await
+}
+
+/// Deletes every entry in the thread along with the thread itself
+pub async fn wipe_thread(settings: &DatabaseSettings, thread: &str) -> anyhow::Result<()> {
+    let mut connection = connect_checked(settings).await?;
+    let query = format!(
+        "DELETE FROM {} WHERE {} = $1",
+        MESSAGES_TABLE, MESSAGE_THREAD
+    );
+    sqlx::query(&query)
+        .bind(thread)
+        .execute(&mut connection)
+        .await?;
+    let query = format!("DELETE FROM {} WHERE {} = $1", THREADS_TABLE, THREAD_NAME);
+    sqlx::query(&query)
+        .bind(thread)
+        .execute(&mut connection)
+        .await?;
+    Ok(())
+}
@@ -1,8 +1,23 @@
+pub mod budget;
 pub mod chat;
-use super::comms::{backend::*, FrontendRequest};
+pub mod config;
+pub mod database;
+pub mod inspector;
+pub mod long_term;
+pub mod retry;
+pub mod semantic;
+pub mod settings;
+pub mod watchdog;
+use super::{
+    comms::{backend::*, FrontendRequest},
+    persistence,
+};
 use chat::{ChatAgentThread, ChatThreadVector};
-use espionox::{agents::Agent, memory::Memory};
-use std::sync::Arc;
+use database::{DatabaseSettings, DatabaseStatus, SharedDatabaseSettings, SharedDatabaseStatus};
+use espionox::memory::MessageVector;
+use semantic::SemanticIndex;
+use settings::SharedCompletionSettings;
+use std::{sync::Arc, time::Duration};
 use tokio::sync::{mpsc, Mutex, RwLock};
 
 #[derive(thiserror::Error, Debug)]
@@ -23,11 +38,18 @@ impl std::fmt::Display for BackendError {
         }
     }
 }
+const DATABASE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
 #[derive(Debug)]
 pub struct AppBackend {
     // pub agent_thread_names: Vec<String>,
     agent_threads: Arc<RwLock<ChatThreadVector>>,
+    settings: SharedCompletionSettings,
+    database: SharedDatabaseSettings,
+    database_status: SharedDatabaseStatus,
+    semantic_index: Arc<Mutex<SemanticIndex>>,
     main_thread: Option<BackendThread>,
+    database_monitor: Option<BackendThread>,
     sender: Arc<BackendSender>,
     receiver: Arc<Mutex<BackendCommandReceiver>>,
 }
@@ -38,48 +60,191 @@ impl AppBackend {
         receiver: mpsc::Receiver<BackendCommand>,
     ) -> Self {
         let sender = Arc::new(sender.into());
-        let agent_threads = Self::init_default_agent_threads(Arc::clone(&sender))
-            .expect("Failed to init default threads");
-        let agent_threads = Arc::new(agent_threads);
+        let settings = Arc::new(RwLock::new(
+            persistence::settings::load_completion_settings(),
+        ));
+        // Read back from the environment `main` set rather than from the settings file,
+        // so the health check, spool replay and every long term write use whatever
+        // espionox actually connects to
+        let database = Arc::new(DatabaseSettings::from_env());
+        let database_status = Arc::new(RwLock::new(DatabaseStatus::Unknown));
+        let agent_threads = Self::init_agent_threads(
+            Arc::clone(&sender),
+            Arc::clone(&settings),
+            Arc::clone(&database),
+            Arc::clone(&database_status),
+        );
+        let agent_threads = Arc::new(RwLock::new(agent_threads));
         let mut backend = Self {
             // agent_thread_names,
             agent_threads,
+            settings,
+            database,
+            database_status,
+            semantic_index: Arc::new(Mutex::new(SemanticIndex::default())),
             main_thread: None,
+            database_monitor: None,
             sender: sender.into(),
             receiver: Arc::new(Mutex::new(receiver.into())),
         };
         backend
             .spawn_main_thread()
             .expect("Failed to spawn main backend thread");
+        backend.spawn_database_monitor();
         backend
     }
 
-    fn init_default_agent_threads(
-        sender: Arc<BackendSender>,
-    ) -> Result<RwLock<ChatThreadVector>, BackendError> {
-        let names = vec!["Chat Agent", "Long Term Agent"];
-        let st_agent_thread = ChatAgentThread::new(names[0], Agent::default(), Arc::clone(&sender));
-
-        let ltm = Memory::build().long_term_thread(names[1]).finished();
-        let lt_agent = Agent {
-            memory: ltm,
-            ..Default::default()
-        };
-        let lt_agent_thread =
-            ChatAgentThread::new(names[1], lt_agent.to_owned(), Arc::clone(&sender));
-
-        let agents = vec![st_agent_thread, lt_agent_thread];
+    /// Periodically checks the saved database, telling the frontend whenever it comes up
+    /// or goes down
+    fn spawn_database_monitor(&mut self) {
+        let sender = Arc::clone(&self.sender);
+        let database = Arc::clone(&self.database);
+        let status = Arc::clone(&self.database_status);
+        let handle = tokio::spawn(async move {
+            loop {
+                let settings = database.as_ref().clone();
+                Self::refresh_database_status(settings, Arc::clone(&status), Arc::clone(&sender))
+                    .await?;
+                tokio::time::sleep(DATABASE_CHECK_INTERVAL).await;
+            }
+        });
+        self.database_monitor = Some(BackendThread::from(handle));
+    }
 
-        for name in names.iter() {
-            let frontend_request = FrontendRequest::NewChatThread(name.to_string());
-            sender.try_send(frontend_request).map_err(|err| {
+    /// Chat threads watch `status` to drop or pick long term memory back up. Anything
+    /// spooled while the database was down is replayed as soon as it's back
+    async fn refresh_database_status(
+        settings: DatabaseSettings,
+        status: SharedDatabaseStatus,
+        sender: Arc<BackendSender>,
+    ) -> Result<(), BackendError> {
+        let new_status = settings.check_connection().await;
+        let previous = std::mem::replace(&mut *status.write().await, new_status.clone());
+        if previous == new_status {
+            return Ok(());
+        }
+        tracing::info!("Database status changed: {:?}", new_status);
+        if new_status == DatabaseStatus::Connected {
+            match long_term::replay_spool().await {
+                Ok(0) => {}
+                Ok(replayed) => tracing::info!("Replayed {} spooled LTM writes", replayed),
+                Err(err) => tracing::warn!("Couldn't replay spooled LTM writes: {:?}", err),
+            }
+        }
+        sender
+            .send(FrontendRequest::DatabaseStatus(new_status))
+            .await
+            .map_err(|err| {
                 BackendError::Unexpected(anyhow::anyhow!(
-                    "Error sending command to agent thread: {:?}",
+                    "Error sending request to frontend: {:?}",
                     err
                 ))
-            })?
+            })?;
+        Ok(())
+    }
+
+    /// Runs a database job off the main loop and sends whatever it reports back
+    fn spawn_reply(
+        sender: Arc<BackendSender>,
+        job: impl std::future::Future<Output = Vec<FrontendRequest>> + Send + 'static,
+    ) {
+        tokio::spawn(async move {
+            for request in job.await.into_iter() {
+                if let Err(err) = sender.send(request).await {
+                    tracing::warn!("Couldn't send request to frontend: {:?}", err);
+                }
+            }
+        });
+    }
+
+    /// Checks `settings` off the main loop and wraps the result with `request`
+    fn spawn_database_check(
+        sender: Arc<BackendSender>,
+        settings: DatabaseSettings,
+        request: fn(DatabaseStatus) -> FrontendRequest,
+    ) {
+        tokio::spawn(async move {
+            let status = settings.check_connection().await;
+            if let Err(err) = sender.send(request(status)).await {
+                tracing::warn!("Couldn't send database status to frontend: {:?}", err);
+            }
+        });
+    }
+
+    /// Restores every saved chat, then adds whichever startup chats weren't saved. The
+    /// frontend doesn't hear about them until `announce_threads`
+    fn init_agent_threads(
+        sender: Arc<BackendSender>,
+        settings: SharedCompletionSettings,
+        database: SharedDatabaseSettings,
+        database_status: SharedDatabaseStatus,
+    ) -> ChatThreadVector {
+        let mut agents: Vec<ChatAgentThread> = persistence::chats::load_agents()
+            .into_iter()
+            .map(|saved| {
+                tracing::info!("Restoring {} chat", saved.name);
+                let mut thread = ChatAgentThread::new(
+                    &saved.name,
+                    saved.config,
+                    persistence::message_vector(&saved.memory),
+                    Arc::clone(&sender),
+                    Arc::clone(&settings),
+                    Arc::clone(&database),
+                    Arc::clone(&database_status),
+                );
+                thread.set_retry_override(saved.retry_override);
+                thread
+            })
+            .collect();
+
+        // Chats restored from before startup chats were tracked count as seeded, so
+        // deleting them later sticks too
+        let mut seeded = persistence::startup::seeded_startup_chats();
+        let seeded_before = seeded.clone();
+        for startup_chat in persistence::startup::load_startup_config().chats.iter() {
+            if !seeded.insert(startup_chat.name.to_owned())
+                || agents.iter().any(|thread| thread.name == startup_chat.name)
+            {
+                continue;
+            }
+            let (config, init_prompt) = startup_chat.agent();
+            agents.push(ChatAgentThread::new(
+                &startup_chat.name,
+                config,
+                init_prompt,
+                Arc::clone(&sender),
+                Arc::clone(&settings),
+                Arc::clone(&database),
+                Arc::clone(&database_status),
+            ));
+        }
+        if seeded != seeded_before {
+            if let Err(err) = persistence::startup::save_seeded_startup_chats(&seeded) {
+                tracing::warn!("Couldn't record the startup chats: {:?}", err);
+            }
+        }
+
+        ChatThreadVector::from(agents)
+    }
+
+    /// Waits on the frontend rather than `try_send`ing, a workspace can hold more chats
+    /// than the channel has room for
+    async fn announce_threads(
+        sender: &BackendSender,
+        threads: &ChatThreadVector,
+    ) -> Result<(), BackendError> {
+        for (name, config) in threads.configs().into_iter() {
+            sender
+                .send(FrontendRequest::NewChatThread { name, config })
+                .await
+                .map_err(|err| {
+                    BackendError::Unexpected(anyhow::anyhow!(
+                        "Error sending request to frontend: {:?}",
+                        err
+                    ))
+                })?;
         }
-        Ok(RwLock::new(ChatThreadVector::from(agents)))
+        Ok(())
     }
 
     // pub fn buffer(&self, agent: &Agent) -> anyhow::Result<Arc<MessageVector>> {
@@ -91,7 +256,12 @@ impl AppBackend {
         let receiver = Arc::clone(&self.receiver);
         let outer_sender = Arc::clone(&self.sender);
         let agent_threads = Arc::clone(&self.agent_threads);
+        let settings = Arc::clone(&self.settings);
+        let database = Arc::clone(&self.database);
+        let database_status = Arc::clone(&self.database_status);
+        let semantic_index = Arc::clone(&self.semantic_index);
         let handle = tokio::spawn(async move {
+            Self::announce_threads(&outer_sender, &*agent_threads.read().await).await?;
             loop {
                 agent_threads
                     .read()
@@ -104,12 +274,23 @@ impl AppBackend {
                     .receive_command()?
                 {
                     match command {
-                        BackendCommand::NewChatThread { name, agent } => {
+                        BackendCommand::NewChatThread {
+                            name,
+                            config,
+                            init_prompt,
+                        } => {
                             tracing::info!("Received command to create new chat thread: {}", name);
-                            let new_thread =
-                                ChatAgentThread::new(&name, agent, Arc::clone(&outer_sender));
+                            let new_thread = ChatAgentThread::new(
+                                &name,
+                                config.clone(),
+                                init_prompt,
+                                Arc::clone(&outer_sender),
+                                Arc::clone(&settings),
+                                Arc::clone(&database),
+                                Arc::clone(&database_status),
+                            );
                             agent_threads.write().await.push(new_thread);
-                            let frontend_request = FrontendRequest::NewChatThread(name);
+                            let frontend_request = FrontendRequest::NewChatThread { name, config };
                             outer_sender.send(frontend_request).await.map_err(|err| {
                                 BackendError::Unexpected(anyhow::anyhow!(
                                     "Error sending command to agent thread: {:?}",
@@ -117,6 +298,49 @@ impl AppBackend {
                                 ))
                             })?
                         }
+                        BackendCommand::ImportChats { chats } => {
+                            tracing::info!("Importing {} chats", chats.len());
+                            for ImportedThread {
+                                name,
+                                config,
+                                history,
+                            } in chats.into_iter()
+                            {
+                                let mut new_thread = ChatAgentThread::new(
+                                    &name,
+                                    config.clone(),
+                                    MessageVector::init(),
+                                    Arc::clone(&outer_sender),
+                                    Arc::clone(&settings),
+                                    Arc::clone(&database),
+                                    Arc::clone(&database_status),
+                                );
+                                // Spawned here rather than at the top of the loop so the
+                                // history can be handed over straight away
+                                new_thread.spawn_chat_thread()?;
+                                if let Some(sender) = &new_thread.sender {
+                                    sender
+                                        .send(chat::ChatAgentMutation::RestoreHistory(history))
+                                        .await
+                                        .map_err(|err| {
+                                            BackendError::Unexpected(anyhow::anyhow!(
+                                                "Error sending command to agent thread: {:?}",
+                                                err
+                                            ))
+                                        })?
+                                }
+                                agent_threads.write().await.push(new_thread);
+                                outer_sender
+                                    .send(FrontendRequest::NewChatThread { name, config })
+                                    .await
+                                    .map_err(|err| {
+                                        BackendError::Unexpected(anyhow::anyhow!(
+                                            "Error sending request to frontend: {:?}",
+                                            err
+                                        ))
+                                    })?
+                            }
+                        }
                         BackendCommand::StreamedCompletion { agent_name, prompt } => {
                             let threads_lock = agent_threads.read().await;
                             let agent_thread = threads_lock
@@ -138,9 +362,32 @@ impl AppBackend {
                             }
                         }
 
+                        BackendCommand::RetryLastPrompt { agent_name } => {
+                            let threads_lock = agent_threads.read().await;
+                            let agent_thread = threads_lock
+                                .get_by_name(&agent_name)
+                                .expect("Failed to get chat thread");
+                            if let Some(sender) = &agent_thread.sender {
+                                sender
+                                    .send(chat::ChatAgentMutation::RetryLastPrompt)
+                                    .await
+                                    .map_err(|err| {
+                                        BackendError::Unexpected(anyhow::anyhow!(
+                                            "Error sending command to agent thread: {:?}",
+                                            err
+                                        ))
+                                    })?
+                            } else {
+                                tracing::warn!("Couldn't get sender from {} agent", agent_name);
+                            }
+                        }
+
                         BackendCommand::RemoveChatThread { name } => {
                             tracing::info!("Removing {} agent thread", name);
                             agent_threads.write().await.remove_by_name(&name);
+                            if let Err(err) = persistence::chats::remove_chat(&name) {
+                                tracing::warn!("Failed to remove saved {} chat: {:?}", name, err);
+                            }
                         }
 
                         BackendCommand::PushToAgentMemory {
@@ -155,7 +402,7 @@ impl AppBackend {
 
                             if let Some(sender) = &agent_thread.sender {
                                 sender
-                                    .send(chat::ChatAgentMutation::PushMessage(message))
+                                    .send(chat::ChatAgentMutation::PushMessages(vec![message]))
                                     .await
                                     .map_err(|err| {
                                         BackendError::Unexpected(anyhow::anyhow!(
@@ -167,6 +414,334 @@ impl AppBackend {
                                 tracing::warn!("Couldn't get sender from {} agent", agent_name);
                             }
                         }
+
+                        BackendCommand::PushHistoryToAgentMemory {
+                            agent_name,
+                            messages,
+                        } => {
+                            tracing::info!(
+                                "Pushing {} messages to {} agent memory",
+                                messages.len(),
+                                agent_name
+                            );
+                            let threads_lock = agent_threads.read().await;
+                            let agent_thread = threads_lock
+                                .get_by_name(&agent_name)
+                                .expect("Failed to get agent thread");
+
+                            if let Some(sender) = &agent_thread.sender {
+                                sender
+                                    .send(chat::ChatAgentMutation::PushMessages(messages))
+                                    .await
+                                    .map_err(|err| {
+                                        BackendError::Unexpected(anyhow::anyhow!(
+                                            "Error sending command to agent thread: {:?}",
+                                            err
+                                        ))
+                                    })?
+                            } else {
+                                tracing::warn!("Couldn't get sender from {} agent", agent_name);
+                            }
+                        }
+
+                        BackendCommand::SetCompletionSettings {
+                            settings: new_settings,
+                        } => {
+                            tracing::info!("Updating global completion settings");
+                            if let Err(err) =
+                                persistence::settings::save_completion_settings(&new_settings)
+                            {
+                                tracing::warn!("Failed to save completion settings: {:?}", err);
+                            }
+                            *settings.write().await = new_settings;
+                        }
+
+                        BackendCommand::SwitchWorkspace { name } => {
+                            tracing::info!("Switching to {} workspace", name);
+                            let mut threads_lock = agent_threads.write().await;
+                            // Every chat has stopped before the data directory changes, so
+                            // one in the middle of saving can't land in the new workspace
+                            threads_lock.close_all().await;
+                            if let Err(err) = persistence::workspaces::set_current_workspace(&name)
+                            {
+                                tracing::warn!("Couldn't switch to {} workspace: {:?}", name, err);
+                                let request = FrontendRequest::WorkspaceSwitchFailed {
+                                    workspace: name,
+                                    error: err.to_string(),
+                                };
+                                outer_sender.send(request).await.map_err(|err| {
+                                    BackendError::Unexpected(anyhow::anyhow!(
+                                        "Error sending request to frontend: {:?}",
+                                        err
+                                    ))
+                                })?;
+                                // Still the old workspace, its chats come back from what
+                                // they saved and are handed to the frontend, which cleared them
+                                *threads_lock = Self::init_agent_threads(
+                                    Arc::clone(&outer_sender),
+                                    Arc::clone(&settings),
+                                    Arc::clone(&database),
+                                    Arc::clone(&database_status),
+                                );
+                                Self::announce_threads(&outer_sender, &threads_lock).await?;
+                                continue;
+                            }
+                            *settings.write().await =
+                                persistence::settings::load_completion_settings();
+                            outer_sender
+                                .send(FrontendRequest::WorkspaceSwitched(name))
+                                .await
+                                .map_err(|err| {
+                                    BackendError::Unexpected(anyhow::anyhow!(
+                                        "Error sending request to frontend: {:?}",
+                                        err
+                                    ))
+                                })?;
+                            *threads_lock = Self::init_agent_threads(
+                                Arc::clone(&outer_sender),
+                                Arc::clone(&settings),
+                                Arc::clone(&database),
+                                Arc::clone(&database_status),
+                            );
+                            Self::announce_threads(&outer_sender, &threads_lock).await?;
+                        }
+
+                        BackendCommand::SetDatabaseSettings {
+                            settings: new_settings,
+                        } => {
+                            tracing::info!("Saving database settings");
+                            let restart_required =
+                                match persistence::database::save_database_settings(&new_settings) {
+                                    Ok(_) => new_settings != *database,
+                                    Err(err) => {
+                                        tracing::warn!(
+                                            "Failed to save database settings: {:?}",
+                                            err
+                                        );
+                                        false
+                                    }
+                                };
+                            Self::spawn_reply(Arc::clone(&outer_sender), async move {
+                                vec![FrontendRequest::DatabaseRestartRequired(restart_required)]
+                            });
+                        }
+
+                        BackendCommand::TestDatabaseConnection {
+                            settings: candidate,
+                        } => {
+                            tracing::info!("Testing connection to {}", candidate.host);
+                            Self::spawn_database_check(
+                                Arc::clone(&outer_sender),
+                                candidate,
+                                FrontendRequest::DatabaseTested,
+                            );
+                        }
+
+                        BackendCommand::ListLtmThreads => {
+                            let settings = database.as_ref().clone();
+                            Self::spawn_reply(Arc::clone(&outer_sender), async move {
+                                let threads = long_term::list_threads(&settings).await;
+                                vec![FrontendRequest::LtmThreads(
+                                    threads.map_err(|err| err.to_string()),
+                                )]
+                            });
+                        }
+
+                        BackendCommand::LoadLtmThread { thread } => {
+                            let settings = database.as_ref().clone();
+                            Self::spawn_reply(Arc::clone(&outer_sender), async move {
+                                let entries = long_term::load_thread(&settings, &thread).await;
+                                vec![FrontendRequest::LtmEntries {
+                                    thread,
+                                    entries: entries.map_err(|err| err.to_string()),
+                                }]
+                            });
+                        }
+
+                        BackendCommand::DeleteLtmEntry { thread, id } => {
+                            tracing::info!(
+                                "Deleting entry {} from {} long term thread",
+                                id,
+                                thread
+                            );
+                            let settings = database.as_ref().clone();
+                            Self::spawn_reply(Arc::clone(&outer_sender), async move {
+                                let entries = match long_term::delete_entry(&settings, &id).await {
+                                    Ok(_) => long_term::load_thread(&settings, &thread).await,
+                                    Err(err) => Err(err),
+                                };
+                                vec![FrontendRequest::LtmEntries {
+                                    thread,
+                                    entries: entries.map_err(|err| err.to_string()),
+                                }]
+                            });
+                        }
+
+                        BackendCommand::WipeLtmThread { thread } => {
+                            tracing::info!("Wiping {} long term thread", thread);
+                            let settings = database.as_ref().clone();
+                            Self::spawn_reply(Arc::clone(&outer_sender), async move {
+                                if let Err(err) = long_term::wipe_thread(&settings, &thread).await {
+                                    return vec![FrontendRequest::LtmEntries {
+                                        thread,
+                                        entries: Err(err.to_string()),
+                                    }];
+                                }
+                                let threads = long_term::list_threads(&settings).await;
+                                vec![FrontendRequest::LtmThreads(
+                                    threads.map_err(|err| err.to_string()),
+                                )]
+                            });
+                        }
+
+                        BackendCommand::RememberInLongTerm {
+                            agent_name,
+                            message,
+                        } => {
+                            // Saved by the chat's own agent, through the long term memory it
+                            // already has
+                            let threads_lock = agent_threads.read().await;
+                            let sender = threads_lock
+                                .get_by_name(&agent_name)
+                                .and_then(|thread| thread.sender.clone());
+                            drop(threads_lock);
+                            match sender {
+                                Some(sender) => sender
+                                    .send(chat::ChatAgentMutation::Remember(message))
+                                    .await
+                                    .map_err(|err| {
+                                        BackendError::Unexpected(anyhow::anyhow!(
+                                            "Error sending command to agent thread: {:?}",
+                                            err
+                                        ))
+                                    })?,
+                                None => Self::spawn_reply(Arc::clone(&outer_sender), async move {
+                                    vec![FrontendRequest::RememberedInLongTerm {
+                                        chat_name: agent_name,
+                                        result: Err("This chat isn't running".to_string()),
+                                    }]
+                                }),
+                            }
+                        }
+
+                        BackendCommand::FindRelatedContext { agent_name, query } => {
+                            let recall_settings = settings.read().await.semantic_recall.clone();
+                            if !recall_settings.enabled {
+                                continue;
+                            }
+                            let database_settings =
+                                match *database_status.read().await == DatabaseStatus::Connected {
+                                    true => Some(database.as_ref().clone()),
+                                    false => None,
+                                };
+                            let index = Arc::clone(&semantic_index);
+                            Self::spawn_reply(Arc::clone(&outer_sender), async move {
+                                let snippets = index
+                                    .lock()
+                                    .await
+                                    .find_related(
+                                        &recall_settings,
+                                        &query,
+                                        &agent_name,
+                                        database_settings.as_ref(),
+                                    )
+                                    .await
+                                    .unwrap_or_else(|err| {
+                                        tracing::warn!("Related context search failed: {:?}", err);
+                                        vec![]
+                                    });
+                                vec![FrontendRequest::RelatedContext {
+                                    chat_name: agent_name,
+                                    query,
+                                    snippets,
+                                }]
+                            });
+                        }
+
+                        BackendCommand::InspectAgentMemory { agent_name } => {
+                            let threads_lock = agent_threads.read().await;
+                            let agent_thread = threads_lock
+                                .get_by_name(&agent_name)
+                                .expect("Failed to get agent thread");
+
+                            if let Some(sender) = &agent_thread.sender {
+                                sender
+                                    .send(chat::ChatAgentMutation::Inspect)
+                                    .await
+                                    .map_err(|err| {
+                                        BackendError::Unexpected(anyhow::anyhow!(
+                                            "Error sending command to agent thread: {:?}",
+                                            err
+                                        ))
+                                    })?
+                            }
+                        }
+
+                        BackendCommand::SetPinnedMessage {
+                            agent_name,
+                            message,
+                            pinned,
+                        } => {
+                            let threads_lock = agent_threads.read().await;
+                            let mut agent_thread = threads_lock
+                                .get_by_name(&agent_name)
+                                .expect("Failed to get agent thread");
+                            agent_thread.config.set_pinned(message.clone(), pinned);
+
+                            if let Some(sender) = &agent_thread.sender {
+                                sender
+                                    .send(chat::ChatAgentMutation::SetPinned { message, pinned })
+                                    .await
+                                    .map_err(|err| {
+                                        BackendError::Unexpected(anyhow::anyhow!(
+                                            "Error sending command to agent thread: {:?}",
+                                            err
+                                        ))
+                                    })?
+                            }
+                        }
+
+                        BackendCommand::SetChatRecall { agent_name, recall } => {
+                            tracing::info!("Setting recall mode for {} agent", agent_name);
+                            let threads_lock = agent_threads.read().await;
+                            let mut agent_thread = threads_lock
+                                .get_by_name(&agent_name)
+                                .expect("Failed to get agent thread");
+                            agent_thread.config.recall = recall.clone();
+
+                            if let Some(sender) = &agent_thread.sender {
+                                sender
+                                    .send(chat::ChatAgentMutation::SetRecall(recall))
+                                    .await
+                                    .map_err(|err| {
+                                        BackendError::Unexpected(anyhow::anyhow!(
+                                            "Error sending command to agent thread: {:?}",
+                                            err
+                                        ))
+                                    })?
+                            }
+                        }
+
+                        BackendCommand::SetChatRetryPolicy { agent_name, policy } => {
+                            tracing::info!("Setting retry policy for {} agent", agent_name);
+                            let threads_lock = agent_threads.read().await;
+                            let mut agent_thread = threads_lock
+                                .get_by_name(&agent_name)
+                                .expect("Failed to get agent thread");
+                            agent_thread.set_retry_override(policy.clone());
+
+                            if let Some(sender) = &agent_thread.sender {
+                                sender
+                                    .send(chat::ChatAgentMutation::SetRetryPolicy(policy))
+                                    .await
+                                    .map_err(|err| {
+                                        BackendError::Unexpected(anyhow::anyhow!(
+                                            "Error sending command to agent thread: {:?}",
+                                            err
+                                        ))
+                                    })?
+                            }
+                        }
                     };
                 } else {
                     tokio::time::sleep(std::time::Duration::from_secs(2)).await;
@@ -0,0 +1,152 @@
+use super::BackendError;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub backoff_multiplier: f32,
+    pub max_backoff: Duration,
+    pub retry_on: RetryableErrors,
+}
+
+/// Which kinds of transient failures are worth another attempt
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryableErrors {
+    pub rate_limit: bool,
+    pub timeout: bool,
+    pub server_error: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransientError {
+    RateLimit,
+    Timeout,
+    ServerError,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+            backoff_multiplier: 2.0,
+            max_backoff: Duration::from_secs(30),
+            retry_on: RetryableErrors::default(),
+        }
+    }
+}
+
+impl Default for RetryableErrors {
+    fn default() -> Self {
+        Self {
+            rate_limit: true,
+            timeout: true,
+            server_error: true,
+        }
+    }
+}
+
+impl TransientError {
+    /// Goes by the HTTP or IO error somewhere in the error's sources. espionox's errors
+    /// don't always keep those as a source, so failing that it goes by what the errors say
+    pub fn classify(err: &BackendError) -> Option<Self> {
+        let err = match err {
+            BackendError::Unexpected(err) => err,
+            BackendError::Recoverable => return None,
+        };
+        err.chain()
+            .find_map(|source| {
+                if let Some(http) = source.downcast_ref::<reqwest::Error>() {
+                    return Self::from_http(http);
+                }
+                match source.downcast_ref::<std::io::Error>()?.kind() {
+                    std::io::ErrorKind::TimedOut => Some(Self::Timeout),
+                    _ => None,
+                }
+            })
+            .or_else(|| {
+                err.chain()
+                    .find_map(|source| Self::from_message(&source.to_string()))
+            })
+    }
+
+    /// What reqwest and the OpenAI API put in their messages for each kind
+    fn from_message(message: &str) -> Option<Self> {
+        let message = message.to_lowercase();
+        let mentions = |phrases: &[&str]| phrases.iter().any(|phrase| message.contains(phrase));
+        if mentions(&["429", "too many requests", "rate limit"]) {
+            return Some(Self::RateLimit);
+        }
+        if mentions(&[
+            "500 internal server error",
+            "502 bad gateway",
+            "503 service unavailable",
+            "504 gateway timeout",
+            "server_error",
+            "overloaded",
+        ]) {
+            return Some(Self::ServerError);
+        }
+        if mentions(&["timed out", "timeout"]) {
+            return Some(Self::Timeout);
+        }
+        None
+    }
+
+    fn from_http(err: &reqwest::Error) -> Option<Self> {
+        if err.is_timeout() {
+            return Some(Self::Timeout);
+        }
+        match err.status()? {
+            StatusCode::TOO_MANY_REQUESTS => Some(Self::RateLimit),
+            status if status.is_server_error() => Some(Self::ServerError),
+            _ => None,
+        }
+    }
+}
+
+impl RetryableErrors {
+    fn allows(&self, err: TransientError) -> bool {
+        match err {
+            TransientError::RateLimit => self.rate_limit,
+            TransientError::Timeout => self.timeout,
+            TransientError::ServerError => self.server_error,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn no_retries() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// How long to wait after the given (1-based) failed attempt
+    pub fn backoff_for(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1) as i32;
+        let secs = self.initial_backoff.as_secs_f32() * self.backoff_multiplier.powi(exponent);
+        // A big enough multiplier overflows to infinity, and a hand-edited one can be
+        // negative or NaN, none of which is a duration
+        Duration::try_from_secs_f32(secs)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+
+    /// Returns the delay before the next attempt, or `None` if the error should be surfaced
+    pub fn retry_delay(&self, err: &BackendError, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+        match TransientError::classify(err) {
+            Some(transient) if self.retry_on.allows(transient) => Some(self.backoff_for(attempt)),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use super::{database::DatabaseSettings, long_term};
+use crate::logic::persistence::chats::{display_versions, load_display_at};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Longer messages are cut down before they're embedded or offered as context
+const MAX_SNIPPET_CHARS: usize = 1200;
+const MIN_SNIPPET_CHARS: usize = 20;
+const EMBED_BATCH_SIZE: usize = 64;
+/// Long term memory can change from other chats and other apps, but reading every thread
+/// on each lookup is too slow
+const LONG_TERM_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Settings for offering related snippets from other chats and long term memory while a
+/// prompt is being written
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SemanticRecallSettings {
+    pub enabled: bool,
+    pub provider: EmbeddingProviderKind,
+    pub max_results: usize,
+    /// Cosine similarity below which a snippet isn't worth showing
+    pub min_score: f32,
+}
+
+impl Default for SemanticRecallSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            provider: EmbeddingProviderKind::default(),
+            max_results: 3,
+            min_score: 0.25,
+        }
+    }
+}
+
+pub type EmbeddingFuture<'a> =
+    Pin<Box<dyn Future<Output = anyhow::Result<Vec<Vec<f32>>>> + Send + 'a>>;
+
+/// Turns text into vectors. Vectors are only ever compared with others from the same
+/// provider, so switching providers throws the index away
+pub trait EmbeddingProvider: std::fmt::Debug + Send + Sync {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> EmbeddingFuture<'a>;
+}
+
+/// Providers that can be picked in settings, add a variant here to plug in another
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum EmbeddingProviderKind {
+    #[default]
+    LocalHashing,
+}
+
+impl std::fmt::Display for EmbeddingProviderKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LocalHashing => write!(f, "Local (hashing)"),
+        }
+    }
+}
+
+impl EmbeddingProviderKind {
+    pub fn all() -> Vec<Self> {
+        vec![Self::LocalHashing]
+    }
+
+    pub fn provider(&self) -> Box<dyn EmbeddingProvider> {
+        match self {
+            Self::LocalHashing => Box::new(HashingEmbedder::default()),
+        }
+    }
+}
+
+const STOPWORDS: [&str; 16] = [
+    "the", "and", "for", "you", "that", "this", "with", "are", "was", "but", "not", "have", "can",
+    "from", "your", "what",
+];
+
+/// Feature hashing over words and word pairs. Needs no model or network and always gives
+/// the same vector for the same text, which also makes it useful in tests
+#[derive(Debug)]
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self { dimensions: 256 }
+    }
+}
+
+/// FNV-1a, unlike the std hasher it's guaranteed stable between builds
+pub(super) fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+impl HashingEmbedder {
+    fn embed_one(&self, text: &str) -> Vec<f32> {
+        let mut vector = vec![0.0; self.dimensions];
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .map(str::to_lowercase)
+            .filter(|word| word.chars().count() > 2 && !STOPWORDS.contains(&word.as_str()))
+            .collect();
+        let mut add = |feature: &str, weight: f32| {
+            let hash = fnv1a(feature);
+            let sign = match hash >> 63 {
+                0 => 1.0,
+                _ => -1.0,
+            };
+            vector[(hash % self.dimensions as u64) as usize] += sign * weight;
+        };
+        for word in words.iter() {
+            add(word, 1.0);
+        }
+        for pair in words.windows(2) {
+            add(&format!("{} {}", pair[0], pair[1]), 0.5);
+        }
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|x| *x /= norm);
+        }
+        vector
+    }
+}
+
+impl EmbeddingProvider for HashingEmbedder {
+    fn embed<'a>(&'a self, texts: &'a [String]) -> EmbeddingFuture<'a> {
+        Box::pin(async move { Ok(texts.iter().map(|text| self.embed_one(text)).collect()) })
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
+    let norm =
+        a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    match norm > 0.0 {
+        true => dot / norm,
+        false => 0.0,
+    }
+}
+
+/// Something the index can offer, `source` is the chat name or long term thread
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexSource {
+    pub source: String,
+    pub role: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelatedSnippet {
+    pub source: IndexSource,
+    pub score: f32,
+}
+
+impl IndexSource {
+    fn new(source: String, role: &str, content: &str) -> Option<Self> {
+        let content = content.trim();
+        if content.chars().count() < MIN_SNIPPET_CHARS {
+            return None;
+        }
+        let text = match content.chars().count() > MAX_SNIPPET_CHARS {
+            true => format!(
+                "{}…",
+                content.chars().take(MAX_SNIPPET_CHARS).collect::<String>()
+            ),
+            false => content.to_string(),
+        };
+        Some(Self {
+            source,
+            role: role.to_string(),
+            text,
+        })
+    }
+}
+
+/// A chat's messages as the index offers them. System messages are left out, they're
+/// init prompts and pushed files
+fn chat_sources(dir: &Path) -> Vec<IndexSource> {
+    let display = match load_display_at(dir) {
+        Some(recovered) => recovered.display,
+        None => return vec![],
+    };
+    display
+        .messages
+        .iter()
+        .filter(|message| message.role != "system")
+        .filter_map(|message| {
+            IndexSource::new(display.name.to_owned(), &message.role, &message.content)
+        })
+        .collect()
+}
+
+async fn long_term_sources(database: &DatabaseSettings) -> Vec<IndexSource> {
+    let threads = long_term::load_threads(database)
+        .await
+        .unwrap_or_else(|err| {
+            tracing::warn!("Couldn't load long term threads to index: {:?}", err);
+            vec![]
+        });
+    threads
+        .into_iter()
+        .flat_map(|(thread, entries)| {
+            entries
+                .into_iter()
+                .filter(|entry| entry.readable)
+                .filter_map(|entry| {
+                    IndexSource::new(format!("LTM · {}", thread), &entry.role, &entry.content)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Long term memory the index last read and when
+#[derive(Debug)]
+struct LongTermSources {
+    database: DatabaseSettings,
+    read_at: Instant,
+    sources: Vec<IndexSource>,
+}
+
+/// What can be offered and its embeddings, kept between lookups. Chats are only reread
+/// once their display changes on disk, long term memory at most every
+/// `LONG_TERM_REFRESH_INTERVAL`
+#[derive(Debug, Default)]
+pub struct SemanticIndex {
+    provider: Option<(EmbeddingProviderKind, Box<dyn EmbeddingProvider>)>,
+    /// Embeddings by text, so each search only embeds what's new since the last one
+    vectors: HashMap<String, Vec<f32>>,
+    /// By chat directory, so chats from another workspace drop out on their own
+    chats: HashMap<PathBuf, (SystemTime, Vec<IndexSource>)>,
+    long_term: Option<LongTermSources>,
+}
+
+impl SemanticIndex {
+    fn use_provider(&mut self, kind: EmbeddingProviderKind) -> &dyn EmbeddingProvider {
+        if self.provider.as_ref().map(|(current, _)| *current) != Some(kind) {
+            self.vectors.clear();
+            self.provider = Some((kind, kind.provider()));
+        }
+        self.provider.as_ref().unwrap().1.as_ref()
+    }
+
+    async fn embed_missing(
+        &mut self,
+        kind: EmbeddingProviderKind,
+        texts: Vec<String>,
+    ) -> anyhow::Result<()> {
+        // Switching providers clears the vectors, so that has to happen before checking
+        // which are missing
+        self.use_provider(kind);
+        let missing: Vec<String> = texts
+            .into_iter()
+            .filter(|text| !self.vectors.contains_key(text))
+            .collect();
+        let provider = self.use_provider(kind);
+        let mut embedded = vec![];
+        for batch in missing.chunks(EMBED_BATCH_SIZE) {
+            let vectors = provider.embed(batch).await?;
+            embedded.extend(batch.iter().cloned().zip(vectors.into_iter()));
+        }
+        self.vectors.extend(embedded);
+        Ok(())
+    }
+
+    /// Rereads chats whose display changed since the last lookup and forgets deleted
+    /// ones. Long term memory is dropped when the database isn't up
+    async fn refresh(&mut self, database: Option<&DatabaseSettings>) {
+        let versions = display_versions();
+        let current: HashSet<&PathBuf> = versions.iter().map(|(dir, _)| dir).collect();
+        self.chats.retain(|dir, _| current.contains(dir));
+        for (dir, changed) in versions.iter() {
+            if self.chats.get(dir).map(|(indexed, _)| indexed) != Some(changed) {
+                self.chats
+                    .insert(dir.to_owned(), (*changed, chat_sources(dir)));
+            }
+        }
+
+        let stale = match (&self.long_term, database) {
+            (_, None) => {
+                self.long_term = None;
+                false
+            }
+            (None, Some(_)) => true,
+            (Some(long_term), Some(database)) => {
+                long_term.database != *database
+                    || long_term.read_at.elapsed() >= LONG_TERM_REFRESH_INTERVAL
+            }
+        };
+        if let (true, Some(database)) = (stale, database) {
+            self.long_term = Some(LongTermSources {
+                database: database.clone(),
+                read_at: Instant::now(),
+                sources: long_term_sources(database).await,
+            });
+        }
+    }
+
+    /// The best matches for `query` in every chat but `exclude_chat`, plus long term
+    /// memory when `database` is given
+    pub async fn find_related(
+        &mut self,
+        settings: &SemanticRecallSettings,
+        query: &str,
+        exclude_chat: &str,
+        database: Option<&DatabaseSettings>,
+    ) -> anyhow::Result<Vec<RelatedSnippet>> {
+        self.refresh(database).await;
+        let sources: Vec<IndexSource> = self
+            .chats
+            .values()
+            .flat_map(|(_, sources)| sources.iter())
+            .filter(|source| source.source != exclude_chat)
+            .chain(
+                self.long_term
+                    .iter()
+                    .flat_map(|cached| cached.sources.iter()),
+            )
+            .cloned()
+            .collect();
+        self.search(settings, query, sources).await
+    }
+
+    /// The best matches for `query` among `sources`, best first
+    async fn search(
+        &mut self,
+        settings: &SemanticRecallSettings,
+        query: &str,
+        sources: Vec<IndexSource>,
+    ) -> anyhow::Result<Vec<RelatedSnippet>> {
+        let query = query.trim().to_string();
+        let mut texts: HashSet<String> = sources.iter().map(|s| s.text.to_owned()).collect();
+        texts.insert(query.to_owned());
+        // Vectors for messages that have since been deleted aren't worth keeping
+        self.vectors.retain(|text, _| texts.contains(text));
+        self.embed_missing(settings.provider, texts.into_iter().collect())
+            .await?;
+
+        let query_vector = self.vectors.get(&query).cloned().unwrap_or_default();
+        let mut snippets: Vec<RelatedSnippet> = sources
+            .into_iter()
+            .filter_map(|source| {
+                let score = cosine(&query_vector, self.vectors.get(&source.text)?);
+                (score >= settings.min_score).then_some(RelatedSnippet { source, score })
+            })
+            .collect();
+        snippets.sort_by(|a, b| b.score.total_cmp(&a.score));
+        let mut seen = HashSet::new();
+        snippets.retain(|snippet| seen.insert(snippet.source.text.to_owned()));
+        snippets.truncate(settings.max_results);
+        Ok(snippets)
+    }
+}
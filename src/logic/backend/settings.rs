@@ -0,0 +1,15 @@
+use super::{retry::RetryPolicy, semantic::SemanticRecallSettings, watchdog::StallWatchdog};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Settings shared by every chat thread unless a chat overrides them
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompletionSettings {
+    pub retry: RetryPolicy,
+    pub watchdog: StallWatchdog,
+    pub semantic_recall: SemanticRecallSettings,
+}
+
+pub type SharedCompletionSettings = Arc<RwLock<CompletionSettings>>;
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StallWatchdog {
+    pub enabled: bool,
+    pub first_token_timeout: Duration,
+    pub inter_token_timeout: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StallKind {
+    FirstToken,
+    InterToken,
+}
+
+impl Default for StallWatchdog {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            first_token_timeout: Duration::from_secs(60),
+            inter_token_timeout: Duration::from_secs(20),
+        }
+    }
+}
+
+impl StallWatchdog {
+    /// How long to wait for the next token, `None` means wait forever
+    pub fn timeout_for(&self, tokens_received: usize) -> Option<Duration> {
+        if !self.enabled {
+            return None;
+        }
+        match tokens_received {
+            0 => Some(self.first_token_timeout),
+            _ => Some(self.inter_token_timeout),
+        }
+    }
+}
+
+impl StallKind {
+    pub fn from_tokens_received(tokens_received: usize) -> Self {
+        match tokens_received {
+            0 => Self::FirstToken,
+            _ => Self::InterToken,
+        }
+    }
+}
+
+impl std::fmt::Display for StallKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FirstToken => write!(f, "No first token"),
+            Self::InterToken => write!(f, "Token stream stalled"),
+        }
+    }
+}
@@ -1,6 +1,13 @@
 use super::FrontendRequest;
-use crate::backend::BackendError;
-use espionox::{agents::Agent, memory::Message};
+use crate::backend::{
+    config::{AgentConfig, RecallConfig},
+    database::DatabaseSettings,
+    retry::RetryPolicy,
+    settings::CompletionSettings,
+    BackendError,
+};
+use crate::persistence::SavedMessage;
+use espionox::memory::{Message, MessageVector};
 use tokio::{
     sync::mpsc::{self, Receiver, Sender},
     task::JoinHandle,
@@ -12,17 +19,99 @@ pub enum BackendCommand {
         agent_name: String,
         prompt: String,
     },
+    /// Drops the stalled exchange from the agent's memory and prompts with it again
+    RetryLastPrompt {
+        agent_name: String,
+    },
     PushToAgentMemory {
         agent_name: String,
         message: Message,
     },
+    /// Pushes several messages in one go, e.g. every file in a directory
+    PushHistoryToAgentMemory {
+        agent_name: String,
+        messages: Vec<Message>,
+    },
+    /// Creates a thread for each chat and pushes its history, their displays are already
+    /// on disk
+    ImportChats {
+        chats: Vec<ImportedThread>,
+    },
     NewChatThread {
         name: String,
-        agent: Agent,
+        config: AgentConfig,
+        init_prompt: MessageVector,
     },
     RemoveChatThread {
         name: String,
     },
+    SetCompletionSettings {
+        settings: CompletionSettings,
+    },
+    /// Tears down every chat thread and loads the named workspace's chats instead
+    SwitchWorkspace {
+        name: String,
+    },
+    /// Saves the settings and points long term memory at them
+    SetDatabaseSettings {
+        settings: DatabaseSettings,
+    },
+    /// Tries the settings without saving them, answered with `DatabaseTested`
+    TestDatabaseConnection {
+        settings: DatabaseSettings,
+    },
+    /// Answered with `LtmThreads`
+    ListLtmThreads,
+    /// Answered with `LtmEntries`
+    LoadLtmThread {
+        thread: String,
+    },
+    DeleteLtmEntry {
+        thread: String,
+        id: String,
+    },
+    WipeLtmThread {
+        thread: String,
+    },
+    /// Writes straight to the chat's long term thread, or the spool while the database is
+    /// down. Answered with `RememberedInLongTerm`
+    RememberInLongTerm {
+        agent_name: String,
+        message: SavedMessage,
+    },
+    /// Answered with `RelatedContext` when semantic recall is enabled
+    FindRelatedContext {
+        agent_name: String,
+        query: String,
+    },
+    /// Answered with `AgentMemory` once the chat isn't busy with a completion
+    InspectAgentMemory {
+        agent_name: String,
+    },
+    /// A pinned message is never forgotten by a token budget, other caching mechanisms
+    /// ignore pins
+    SetPinnedMessage {
+        agent_name: String,
+        message: SavedMessage,
+        pinned: bool,
+    },
+    /// Rebuilds the chat's agent around its current memory with the new mode
+    SetChatRecall {
+        agent_name: String,
+        recall: RecallConfig,
+    },
+    /// `None` puts the chat back on the global policy
+    SetChatRetryPolicy {
+        agent_name: String,
+        policy: Option<RetryPolicy>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct ImportedThread {
+    pub name: String,
+    pub config: AgentConfig,
+    pub history: Vec<Message>,
 }
 
 unsafe impl Send for BackendCommand {}
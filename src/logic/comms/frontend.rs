@@ -1,6 +1,17 @@
 use super::BackendCommand;
-use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc;
+use crate::logic::backend::{
+    config::AgentConfig,
+    database::DatabaseStatus,
+    inspector::{InspectedMessage, MemoryCompaction},
+    long_term::LtmEntry,
+    semantic::RelatedSnippet,
+    watchdog::StallKind,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::mpsc::{self, error::TrySendError};
 
 pub type FrontendSender = mpsc::Sender<BackendCommand>;
 pub type FrontendReceiver = mpsc::Receiver<FrontendRequest>;
@@ -13,9 +24,83 @@ pub struct FrontendComms {
 
 #[derive(Debug, Clone)]
 pub enum FrontendRequest {
-    StreamToken { token: String, chat_name: String },
-    DoneStreaming { chat_name: String },
-    NewChatThread(String),
+    StreamToken {
+        token: String,
+        chat_name: String,
+    },
+    DoneStreaming {
+        chat_name: String,
+    },
+    /// The config is the one the thread was started with, the agent may not be saved yet
+    NewChatThread {
+        name: String,
+        config: AgentConfig,
+    },
+    RetryingCompletion {
+        chat_name: String,
+        attempt: u32,
+        max_attempts: u32,
+        delay: Duration,
+    },
+    CompletionFailed {
+        chat_name: String,
+        error: String,
+    },
+    /// Sent before the new workspace's `NewChatThread`s
+    WorkspaceSwitched(String),
+    /// The backend stayed in the workspace it was in and resends its `NewChatThread`s
+    WorkspaceSwitchFailed {
+        workspace: String,
+        error: String,
+    },
+    /// The stream was aborted, whatever was streamed so far is kept as incomplete
+    StreamStalled {
+        chat_name: String,
+        kind: StallKind,
+        waited: Duration,
+    },
+    /// Sent for chats with a long term thread when they start and whenever they lose or
+    /// regain the database
+    LongTermMemoryStatus {
+        chat_name: String,
+        online: bool,
+    },
+    /// `Ok` says where the message went
+    RememberedInLongTerm {
+        chat_name: String,
+        result: Result<String, String>,
+    },
+    /// Sent after the completion that triggered it
+    MemoryCompacted {
+        chat_name: String,
+        compaction: MemoryCompaction,
+    },
+    /// What the agent will send with the next prompt
+    AgentMemory {
+        chat_name: String,
+        messages: Vec<InspectedMessage>,
+        /// Only a token budget keeps pinned messages, other caching mechanisms ignore pins
+        pins_apply: bool,
+    },
+    /// Earlier snippets that match a prompt being written, best first
+    RelatedContext {
+        chat_name: String,
+        query: String,
+        snippets: Vec<RelatedSnippet>,
+    },
+    /// Whether the saved database settings can currently be connected to
+    DatabaseStatus(DatabaseStatus),
+    /// Result of a `TestDatabaseConnection`
+    DatabaseTested(DatabaseStatus),
+    /// Sent after `SetDatabaseSettings`, `true` while the saved settings differ from the
+    /// ones the app started with and are still using
+    DatabaseRestartRequired(bool),
+    /// Every long term thread in the database, or why they couldn't be read
+    LtmThreads(Result<Vec<String>, String>),
+    LtmEntries {
+        thread: String,
+        entries: Result<Vec<LtmEntry>, String>,
+    },
 }
 
 #[derive(Default, Debug, Clone)]
@@ -24,6 +109,32 @@ pub struct CurrentExchange {
     pub agent_responses: Vec<FrontendRequest>,
 }
 
+impl FrontendRequest {
+    /// The chat this request is about, if it's about one that should already exist
+    pub fn chat_name(&self) -> Option<&str> {
+        match self {
+            Self::StreamToken { chat_name, .. }
+            | Self::DoneStreaming { chat_name }
+            | Self::RetryingCompletion { chat_name, .. }
+            | Self::CompletionFailed { chat_name, .. }
+            | Self::StreamStalled { chat_name, .. }
+            | Self::LongTermMemoryStatus { chat_name, .. }
+            | Self::RelatedContext { chat_name, .. }
+            | Self::RememberedInLongTerm { chat_name, .. }
+            | Self::AgentMemory { chat_name, .. }
+            | Self::MemoryCompacted { chat_name, .. } => Some(chat_name),
+            Self::NewChatThread { .. }
+            | Self::WorkspaceSwitched(_)
+            | Self::WorkspaceSwitchFailed { .. }
+            | Self::DatabaseStatus(_)
+            | Self::DatabaseTested(_)
+            | Self::DatabaseRestartRequired(_)
+            | Self::LtmThreads(_)
+            | Self::LtmEntries { .. } => None,
+        }
+    }
+}
+
 unsafe impl Send for FrontendRequest {}
 unsafe impl Sync for FrontendRequest {}
 
@@ -34,4 +145,13 @@ impl FrontendComms {
             receiver: Arc::new(Mutex::new(receiver)),
         }
     }
+
+    /// For commands a user can fire off in bulk, the backend only takes one a loop so the
+    /// channel can fill up
+    pub fn try_send(&self, command: BackendCommand) -> anyhow::Result<()> {
+        self.sender.try_send(command).map_err(|err| match err {
+            TrySendError::Full(_) => anyhow::anyhow!("the backend is busy, try again shortly"),
+            TrySendError::Closed(_) => anyhow::anyhow!("the backend has stopped"),
+        })
+    }
 }
@@ -1,12 +1,16 @@
 pub mod backend;
 pub mod comms;
 pub mod pages;
+pub mod persistence;
 pub mod state;
 
 use self::{
-    backend::AppBackend,
-    comms::FrontendComms,
-    pages::{ChatPage, SettingsPage},
+    backend::{database::DatabaseStatus, AppBackend},
+    comms::{BackendCommand, FrontendComms, FrontendRequest},
+    pages::{
+        ChatPage, ComparePage, DialoguePage, GlobalSearch, MemoryPage, PromptsPage, SettingsPage,
+    },
+    persistence::workspaces::{current_workspace, list_workspaces},
     state::State,
 };
 use eframe::egui;
@@ -16,11 +20,30 @@ use tokio::sync::mpsc;
 pub struct MainApplication {
     state: State,
     chat_page: ChatPage,
-    // settings_page: SettingsPage,
+    compare_page: ComparePage,
+    dialogue_page: DialoguePage,
+    prompts_page: PromptsPage,
+    memory_page: MemoryPage,
+    settings_page: SettingsPage,
+    workspace: WorkspaceSwitcher,
+    search: GlobalSearch,
+    database_status: DatabaseStatus,
+    /// Database settings were saved that only take effect after a restart
+    database_restart_required: bool,
     frontend: FrontendComms,
     backend: AppBackend,
 }
 
+#[derive(Debug, Default)]
+struct WorkspaceSwitcher {
+    current: String,
+    /// Set between asking the backend to switch and hearing back
+    pending: Option<String>,
+    /// Why the last switch didn't happen
+    error: Option<String>,
+    new_name: String,
+}
+
 impl Default for MainApplication {
     fn default() -> Self {
         let rt = tokio::runtime::Runtime::new().expect("Unable to create Runtime");
@@ -44,7 +67,18 @@ impl Default for MainApplication {
         Self {
             state: State::default(),
             chat_page: ChatPage::init(),
-            // settings_page: SettingsPage::from(&backend),
+            compare_page: ComparePage::init(),
+            dialogue_page: DialoguePage::init(),
+            prompts_page: PromptsPage::init(),
+            memory_page: MemoryPage::init(),
+            settings_page: SettingsPage::init(),
+            workspace: WorkspaceSwitcher {
+                current: current_workspace(),
+                ..Default::default()
+            },
+            search: GlobalSearch::default(),
+            database_status: DatabaseStatus::Unknown,
+            database_restart_required: false,
             frontend,
             backend,
         }
@@ -61,6 +95,33 @@ impl eframe::App for MainApplication {
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         // ctx.set_style
         self.top_bar_ui(ctx, frame);
+        match self.chat_page.listen_for_chat_updates(&self.frontend, ctx) {
+            Some(FrontendRequest::WorkspaceSwitched(workspace)) => {
+                self.workspace_switched(workspace)
+            }
+            Some(FrontendRequest::WorkspaceSwitchFailed { workspace, error }) => {
+                self.workspace.pending = None;
+                self.workspace.error = Some(format!("Couldn't switch to {}: {}", workspace, error));
+            }
+            Some(FrontendRequest::DatabaseStatus(status)) => self.database_status = status,
+            Some(FrontendRequest::DatabaseTested(status)) => {
+                self.settings_page.database_tested(status)
+            }
+            Some(FrontendRequest::DatabaseRestartRequired(required)) => {
+                self.database_restart_required = required;
+                self.settings_page.set_restart_required(required);
+            }
+            Some(FrontendRequest::LtmThreads(threads)) => self.memory_page.threads_loaded(threads),
+            Some(FrontendRequest::LtmEntries { thread, entries }) => {
+                self.memory_page.entries_loaded(thread, entries)
+            }
+            _ => {}
+        }
+        self.dialogue_page
+            .advance(&mut self.chat_page, &self.frontend, ctx);
+        if self.search.display_results(&mut self.chat_page, ctx) {
+            self.state = State::Chat;
+        }
         Self::display_main_window(ctx, frame, |ui| match self.state {
             State::Chat => {
                 // if !self.backend.max_chat_threads_spawned() {
@@ -69,9 +130,22 @@ impl eframe::App for MainApplication {
                 self.chat_page.display_current_chat(&self.frontend, ui);
                 // let _ = self.backend.listen_for_commands();
             }
+            State::Compare => {
+                self.compare_page
+                    .display(&mut self.chat_page, &self.frontend, ui);
+            }
+            State::Dialogue => {
+                self.dialogue_page
+                    .display(&mut self.chat_page, &self.frontend, ui);
+            }
+            State::Prompts => {
+                self.prompts_page.display(ui);
+            }
+            State::Memory => {
+                self.memory_page.display(&self.frontend, ui);
+            }
             State::Settings => {
-
-                // self.settings_page.display(ui),
+                self.settings_page.display(&self.frontend, ui);
             }
         });
     }
@@ -128,6 +202,75 @@ impl MainApplication {
         });
     }
 
+    fn switch_workspace(&mut self, name: String) {
+        if name == self.workspace.current || self.workspace.pending.is_some() {
+            return;
+        }
+        // Nothing may write to the old workspace's chats once the backend starts switching
+        self.chat_page.clear();
+        self.frontend
+            .sender
+            .try_send(BackendCommand::SwitchWorkspace {
+                name: name.to_owned(),
+            })
+            .unwrap();
+        self.workspace.pending = Some(name);
+        self.workspace.error = None;
+    }
+
+    fn workspace_switched(&mut self, name: String) {
+        self.workspace.current = name;
+        self.workspace.pending = None;
+        self.compare_page = ComparePage::init();
+        self.dialogue_page = DialoguePage::init();
+        self.prompts_page.reload();
+        self.settings_page.reload();
+    }
+
+    fn workspace_switcher_ui(&mut self, ui: &mut egui::Ui) {
+        let mut chosen = None;
+        let selected_text = match &self.workspace.pending {
+            Some(pending) => format!("{} …", pending),
+            None => self.workspace.current.to_owned(),
+        };
+        egui::ComboBox::from_id_source("workspace_switcher")
+            .selected_text(selected_text)
+            .show_ui(ui, |ui| {
+                for workspace in list_workspaces() {
+                    let is_current = workspace == self.workspace.current;
+                    if ui.selectable_label(is_current, &workspace).clicked() {
+                        chosen = Some(workspace);
+                    }
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.workspace.new_name)
+                            .hint_text("New workspace")
+                            .desired_width(100.0),
+                    );
+                    let new_name = self.workspace.new_name.trim().to_string();
+                    if ui
+                        .add_enabled(!new_name.is_empty(), egui::Button::new("➕"))
+                        .clicked()
+                    {
+                        chosen = Some(new_name);
+                        self.workspace.new_name.clear();
+                    }
+                });
+            });
+        if self.workspace.pending.is_some() {
+            ui.spinner();
+        }
+        if let Some(error) = &self.workspace.error {
+            ui.colored_label(egui::Color32::RED, "⚠")
+                .on_hover_text(error);
+        }
+        if let Some(workspace) = chosen {
+            self.switch_workspace(workspace);
+        }
+    }
+
     fn top_bar_ui(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
         use egui::*;
         TopBottomPanel::top("top_bar")
@@ -148,17 +291,55 @@ impl MainApplication {
                             }
                         }
                         self.state = current_state;
+                        ui.separator();
+                        self.workspace_switcher_ui(ui);
                     });
                     ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                         ui.spacing_mut().item_spacing.x = 0.2;
                         ui.visuals_mut().button_frame = false;
                         ui.add_space(8.0);
                         Self::close_maximize_minimize(ui, frame);
+                        ui.add_space(12.0);
+                        self.search.display_field(ui);
+                        ui.add_space(8.0);
+                        self.database_indicator(ui);
                     });
                 });
             });
     }
 
+    fn database_indicator(&mut self, ui: &mut egui::Ui) {
+        let (color, hover) = match &self.database_status {
+            _ if self.database_restart_required => (
+                egui::Color32::GOLD,
+                "Restart required: long term memory keeps using the database the app \
+                 started with until then"
+                    .to_string(),
+            ),
+            DatabaseStatus::Unknown => (
+                egui::Color32::GRAY,
+                "Checking long term memory database…".to_string(),
+            ),
+            DatabaseStatus::Connected => (
+                egui::Color32::GREEN,
+                "Long term memory database connected".to_string(),
+            ),
+            DatabaseStatus::Disconnected(err) => (
+                egui::Color32::RED,
+                format!("Long term memory database unreachable: {}", err),
+            ),
+        };
+        let response = ui
+            .add(
+                egui::Label::new(egui::RichText::new("● DB").color(color))
+                    .sense(egui::Sense::click()),
+            )
+            .on_hover_text(hover);
+        if response.clicked() {
+            self.state = State::Settings;
+        }
+    }
+
     fn close_maximize_minimize(ui: &mut egui::Ui, frame: &mut eframe::Frame) {
         use egui::{Button, RichText};
 
@@ -1,6 +1,28 @@
-use super::modals::AgentInfoModal;
-use crate::logic::comms::{BackendCommand, FrontendComms, FrontendRequest};
-use espionox::memory::{MessageRole, MessageVector, ToMessage};
+use super::{
+    modals::{recall_mode_options, AgentInfoModal},
+    settings::retry_policy_form,
+};
+use crate::logic::{
+    backend::{
+        config::{AgentConfig, RecallConfig},
+        inspector::{estimate_tokens, InspectedMessage, MessageOrigin},
+        retry::RetryPolicy,
+        semantic::RelatedSnippet,
+    },
+    comms::{BackendCommand, FrontendComms, FrontendRequest, ImportedThread},
+    persistence::{
+        self,
+        chats::{
+            display_journal, load_agent, load_display, remove_chat, save_display, DisplayEntry,
+            MessageMeta, SavedDisplay,
+        },
+        export::{ChatTranscript, ExportFormat},
+        import::{parse_conversations, ImportedChat},
+        journal::Journal,
+        SavedMessage,
+    },
+};
+use espionox::memory::{Message, MessageRole, MessageVector, ToMessage};
 
 use eframe::egui;
 
@@ -10,14 +32,38 @@ use eframe::{
     epaint::{Color32, FontId},
 };
 use espionox::core::{Directory, File};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 
 #[derive(Debug)]
 pub struct Chat {
     name: String,
     chat_buffer: MessageVector,
+    message_meta: Vec<MessageMeta>,
     current_exchange: CurrentExchange,
     processing_response: bool,
     error_message: Option<String>,
+    status_message: Option<String>,
+    retry_override: Option<RetryPolicy>,
+    recall: RecallConfig,
+    has_long_term_thread: bool,
+    last_prompt: Option<String>,
+    stalled: bool,
+    /// The chat's long term thread is unreachable, it's running on short term memory
+    ltm_offline: bool,
+    related: RelatedContext,
+    /// A message being saved to long term memory, open in the remember window
+    remember: Option<RememberDraft>,
+    inspector: MemoryInspector,
+    journal: Journal,
+    /// Streamed since the journal last heard about the stream
+    unjournaled_tokens: String,
+    tokens_journaled_at: Instant,
+    /// Set by a search result, scrolled to on the next frame
+    scroll_to_message: Option<usize>,
+    highlighted_message: Option<(usize, Instant)>,
 }
 
 #[derive(Debug)]
@@ -26,12 +72,171 @@ pub struct ChatPage {
     chats: Vec<Chat>,
     create_new_chat_modal_open: bool,
     agent_info_modal: AgentInfoModal,
+    broadcast: Broadcast,
+    import: Option<PendingImport>,
+}
+
+/// A parsed file waiting on the user to pick which conversations to bring in
+#[derive(Debug)]
+struct PendingImport {
+    conversations: Vec<ImportedChat>,
+    selected: Vec<bool>,
+}
+
+/// Chats picked in the side panel to receive the same prompt or push
+#[derive(Default, Debug)]
+struct Broadcast {
+    enabled: bool,
+    targets: Vec<String>,
+    input: String,
+    error_message: Option<String>,
+}
+
+/// Where the agent dropped older messages from memory, expands to whatever replaced them
+fn memory_divider(ui: &mut egui::Ui, message_idx: usize, removed: usize, summary: &str) {
+    let (happened, details) = match summary.is_empty() {
+        true => (
+            "forgotten",
+            "Nothing replaced them, the agent no longer sees them",
+        ),
+        false => ("summarized", summary),
+    };
+    let plural = if removed == 1 { "" } else { "s" };
+    ui.separator();
+    egui::CollapsingHeader::new(
+        RichText::new(format!(
+            "{} earlier message{} {}",
+            removed, plural, happened
+        ))
+        .color(Color32::GRAY),
+    )
+    .id_source(("memory_divider", message_idx))
+    .show(ui, |ui| {
+        ui.label(details);
+    });
+    ui.separator();
+}
+
+pub fn pick_file() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("plaintext", &["txt", "md"])
+        .add_filter(
+            "code",
+            &["rs", "toml", "yaml", "py", "js", "ts", "c", "json"],
+        )
+        .set_directory("/")
+        .pick_file()
+}
+
+pub fn pick_import_file() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .add_filter("chat export", &["json"])
+        .set_directory("/")
+        .pick_file()
+}
+
+pub fn pick_directory() -> Option<PathBuf> {
+    rfd::FileDialog::new().set_directory("/").pick_folder()
+}
+
+/// Streamed tokens are journaled in batches, a crash loses at most this much of a stream
+const TOKEN_JOURNAL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long typing has to pause before related context is looked up
+const RELATED_CONTEXT_DELAY: Duration = Duration::from_millis(700);
+const RELATED_CONTEXT_MIN_CHARS: usize = 12;
+
+/// Snippets from other chats and long term memory that match the prompt being written
+#[derive(Default, Debug)]
+struct RelatedContext {
+    /// The input as of the last frame and when it last changed
+    watched_input: String,
+    changed_at: Option<Instant>,
+    requested: Option<String>,
+    snippets: Vec<RelatedSnippet>,
+    accepted: Vec<usize>,
+}
+
+const INSPECTOR_REFRESH: Duration = Duration::from_secs(2);
+/// Longer messages are cut short in the inspector, pushed directories can be huge
+const INSPECTOR_PREVIEW_CHARS: usize = 2000;
+
+/// The agent's actual memory, which the displayed chat only approximates
+#[derive(Default, Debug)]
+struct MemoryInspector {
+    open: bool,
+    awaiting: bool,
+    /// The answer being awaited predates a pin and is thrown away
+    outdated: bool,
+    refreshed_at: Option<Instant>,
+    messages: Vec<InspectedMessage>,
+    pins_apply: bool,
+}
+
+/// The text starts as the message itself and can be edited down to a summary
+#[derive(Debug)]
+struct RememberDraft {
+    original: SavedMessage,
+    text: String,
+}
+
+impl RememberDraft {
+    /// Edited text is saved as a summary rather than under the message's own role
+    fn to_saved_message(&self) -> SavedMessage {
+        match self.text.trim() == self.original.content.trim() {
+            true => self.original.clone(),
+            false => SavedMessage {
+                role: "system".to_string(),
+                content: self.text.trim().to_string(),
+            },
+        }
+    }
 }
 
 #[derive(Default, Debug, Clone)]
 pub struct CurrentExchange {
     pub user_input: String,
     pub stream_buffer: Option<String>,
+    pub stats: ExchangeStats,
+}
+
+#[derive(Default, Debug, Clone, Copy)]
+pub struct ExchangeStats {
+    pub sent_at: Option<Instant>,
+    pub first_token_at: Option<Instant>,
+    pub finished_at: Option<Instant>,
+    /// Stream chunks received, usually a token or a few each but not guaranteed to be
+    pub chunks: usize,
+}
+
+impl ExchangeStats {
+    fn started_now() -> Self {
+        Self {
+            sent_at: Some(Instant::now()),
+            ..Default::default()
+        }
+    }
+
+    fn record_chunk(&mut self) {
+        if self.first_token_at.is_none() {
+            self.first_token_at = Some(Instant::now());
+        }
+        self.chunks += 1;
+    }
+
+    fn finish(&mut self) {
+        self.finished_at = Some(Instant::now());
+    }
+
+    pub fn time_to_first_token(&self) -> Option<Duration> {
+        Some(self.first_token_at?.duration_since(self.sent_at?))
+    }
+
+    /// Still ticks while the response is streaming
+    pub fn total_time(&self) -> Option<Duration> {
+        let end = self.finished_at.unwrap_or_else(Instant::now);
+        Some(end.duration_since(self.sent_at?))
+    }
 }
 
 impl CurrentExchange {
@@ -58,6 +263,8 @@ impl ChatPage {
             chats,
             create_new_chat_modal_open: false,
             agent_info_modal: AgentInfoModal::new_empty(),
+            broadcast: Broadcast::default(),
+            import: None,
         }
     }
 
@@ -100,62 +307,491 @@ impl ChatPage {
             });
     }
 
-    fn listen_for_chat_updates(&mut self, frontend: &FrontendComms, ctx: &egui::Context) {
+    /// Drops every chat, e.g. while the backend switches workspaces
+    pub fn clear(&mut self) {
+        self.chats.clear();
+        self.current_chat_name = None;
+        self.broadcast = Broadcast::default();
+        self.import = None;
+        self.create_new_chat_modal_open = false;
+    }
+
+    /// Requests that aren't about chats, like a workspace switch or the database status,
+    /// are handed back for the rest of the app
+    pub fn listen_for_chat_updates(
+        &mut self,
+        frontend: &FrontendComms,
+        ctx: &egui::Context,
+    ) -> Option<FrontendRequest> {
         if let Ok(response) = frontend.receiver.lock().unwrap().try_recv() {
             tracing::info!("Frontend got response: {:?}", response);
+            // Chats torn down by a workspace switch can still have requests in flight
+            if let Some(chat_name) = response.chat_name() {
+                if self.chat(chat_name).is_none() {
+                    tracing::warn!("Dropping request for unknown chat {}", chat_name);
+                    return None;
+                }
+            }
             match response {
+                FrontendRequest::WorkspaceSwitched(_) => {
+                    self.clear();
+                    self.agent_info_modal = AgentInfoModal::new_empty();
+                    ctx.request_repaint();
+                    return Some(response);
+                }
+                FrontendRequest::WorkspaceSwitchFailed { .. }
+                | FrontendRequest::DatabaseStatus(_)
+                | FrontendRequest::DatabaseTested(_)
+                | FrontendRequest::DatabaseRestartRequired(_)
+                | FrontendRequest::LtmThreads(_)
+                | FrontendRequest::LtmEntries { .. } => {
+                    ctx.request_repaint();
+                    return Some(response);
+                }
                 FrontendRequest::DoneStreaming { chat_name } => {
                     let chat = self
                         .get_chat_by_name(&chat_name)
                         .expect("Couldn't get chat with that name");
                     chat.processing_response = false;
-                    if chat.current_exchange.stream_buffer.is_some() {
-                        chat.chat_buffer.as_mut().push(
-                            chat.current_exchange
-                                .stream_buffer
-                                .take()
-                                .unwrap()
-                                .to_message_with_role(MessageRole::Assistant),
+                    chat.status_message = None;
+                    chat.current_exchange.stats.finish();
+                    if let Some(response) = chat.current_exchange.stream_buffer.take() {
+                        let meta = MessageMeta {
+                            token_count: Some(estimate_tokens(&response)),
+                            ..MessageMeta::now()
+                        };
+                        chat.push_message(
+                            response.to_message_with_role(MessageRole::Assistant),
+                            meta,
                         );
                     }
+                    chat.compact_if_needed();
                 }
                 FrontendRequest::StreamToken { token, chat_name } => {
                     let chat = self
                         .get_chat_by_name(&chat_name)
                         .expect("Couldn't get chat with that name");
+                    chat.journal_token(&token);
                     chat.current_exchange.push_to_stream_buffer(&token);
+                    chat.current_exchange.stats.record_chunk();
                     tracing::info!(
                         "Updated buffer: {}",
                         chat.current_exchange.stream_buffer.clone().unwrap()
                     );
                     ctx.request_repaint();
                 }
-                FrontendRequest::NewChatThread(chat_name) => {
-                    let new_chat = Chat::init(&chat_name);
+                FrontendRequest::NewChatThread {
+                    name: chat_name,
+                    config,
+                } => {
+                    let new_chat = Chat::restore(&chat_name, &config, frontend);
                     if self.current_chat_name.is_none() {
                         self.current_chat_name = Some(chat_name);
                     }
                     self.chats.push(new_chat);
                 }
+                FrontendRequest::RetryingCompletion {
+                    chat_name,
+                    attempt,
+                    max_attempts,
+                    delay,
+                } => {
+                    let chat = self
+                        .get_chat_by_name(&chat_name)
+                        .expect("Couldn't get chat with that name");
+                    chat.current_exchange.stream_buffer = None;
+                    chat.journal_entry(&DisplayEntry::StreamStarted);
+                    chat.current_exchange.stats = ExchangeStats {
+                        sent_at: chat.current_exchange.stats.sent_at,
+                        ..Default::default()
+                    };
+                    chat.status_message = Some(format!(
+                        "retrying ({}/{}) in {}s…",
+                        attempt,
+                        max_attempts,
+                        delay.as_secs_f32().round()
+                    ));
+                    ctx.request_repaint();
+                }
+                FrontendRequest::RelatedContext {
+                    chat_name,
+                    query,
+                    snippets,
+                } => {
+                    let chat = self
+                        .get_chat_by_name(&chat_name)
+                        .expect("Couldn't get chat with that name");
+                    // Only the latest lookup matters, the prompt may have moved on since
+                    if chat.related.requested.as_ref() == Some(&query) {
+                        chat.related.snippets = snippets;
+                        chat.related.accepted.clear();
+                        ctx.request_repaint();
+                    }
+                }
+                FrontendRequest::RememberedInLongTerm { chat_name, result } => {
+                    let chat = self
+                        .get_chat_by_name(&chat_name)
+                        .expect("Couldn't get chat with that name");
+                    match result {
+                        Ok(note) => {
+                            chat.push_message(
+                                format!("Remembered: {}", note)
+                                    .to_message_with_role(MessageRole::System),
+                                MessageMeta::now(),
+                            );
+                            chat.compact_if_needed();
+                        }
+                        Err(err) => {
+                            chat.error_message = Some(format!("Couldn't remember that: {}", err))
+                        }
+                    }
+                    ctx.request_repaint();
+                }
+                FrontendRequest::MemoryCompacted {
+                    chat_name,
+                    compaction,
+                } => {
+                    let chat = self
+                        .get_chat_by_name(&chat_name)
+                        .expect("Couldn't get chat with that name");
+                    let meta = MessageMeta {
+                        compacted: Some(compaction.removed),
+                        ..MessageMeta::now()
+                    };
+                    chat.push_message(
+                        compaction
+                            .summary
+                            .unwrap_or_default()
+                            .to_message_with_role(MessageRole::System),
+                        meta,
+                    );
+                    chat.compact_if_needed();
+                    ctx.request_repaint();
+                }
+                FrontendRequest::AgentMemory {
+                    chat_name,
+                    messages,
+                    pins_apply,
+                } => {
+                    let chat = self
+                        .get_chat_by_name(&chat_name)
+                        .expect("Couldn't get chat with that name");
+                    let inspector = &mut chat.inspector;
+                    inspector.awaiting = false;
+                    match std::mem::take(&mut inspector.outdated) {
+                        true => inspector.refreshed_at = None,
+                        false => {
+                            inspector.messages = messages;
+                            inspector.pins_apply = pins_apply;
+                            inspector.refreshed_at = Some(Instant::now());
+                        }
+                    }
+                    ctx.request_repaint();
+                }
+                FrontendRequest::LongTermMemoryStatus { chat_name, online } => {
+                    let chat = self
+                        .get_chat_by_name(&chat_name)
+                        .expect("Couldn't get chat with that name");
+                    chat.ltm_offline = !online;
+                    ctx.request_repaint();
+                }
+                FrontendRequest::CompletionFailed { chat_name, error } => {
+                    let chat = self
+                        .get_chat_by_name(&chat_name)
+                        .expect("Couldn't get chat with that name");
+                    chat.processing_response = false;
+                    chat.status_message = None;
+                    chat.current_exchange.stream_buffer = None;
+                    chat.journal_entry(&DisplayEntry::StreamEnded);
+                    chat.current_exchange.stats.finish();
+                    chat.error_message = Some(format!("Completion failed: {}", error));
+                    ctx.request_repaint();
+                }
+                FrontendRequest::StreamStalled {
+                    chat_name,
+                    kind,
+                    waited,
+                } => {
+                    let chat = self
+                        .get_chat_by_name(&chat_name)
+                        .expect("Couldn't get chat with that name");
+                    chat.processing_response = false;
+                    chat.status_message = None;
+                    chat.current_exchange.stats.finish();
+                    if let Some(partial) = chat.current_exchange.stream_buffer.take() {
+                        let meta = MessageMeta {
+                            token_count: Some(estimate_tokens(&partial)),
+                            incomplete: true,
+                            ..MessageMeta::now()
+                        };
+                        chat.push_message(
+                            partial.to_message_with_role(MessageRole::Assistant),
+                            meta,
+                        );
+                    }
+                    chat.error_message =
+                        Some(format!("{} after {}s", kind, waited.as_secs_f32().round()));
+                    chat.stalled = true;
+                    chat.compact_if_needed();
+                    ctx.request_repaint();
+                }
             }
         }
+        None
     }
 
     fn get_chat_by_name(&mut self, name: &str) -> Option<&mut Chat> {
         self.chats.iter_mut().find(|ch| ch.name == name)
     }
 
+    pub fn chat(&self, name: &str) -> Option<&Chat> {
+        self.chats.iter().find(|ch| ch.name == name)
+    }
+
+    pub fn chat_mut(&mut self, name: &str) -> Option<&mut Chat> {
+        self.get_chat_by_name(name)
+    }
+
+    /// Makes `name` the current chat and scrolls it to the message at `message_idx`
+    pub fn jump_to_message(&mut self, name: &str, message_idx: usize) {
+        if let Some(chat) = self.get_chat_by_name(name) {
+            chat.scroll_to_message = Some(message_idx);
+            chat.highlighted_message = Some((message_idx, Instant::now()));
+            self.current_chat_name = Some(name.to_string());
+        }
+    }
+
     pub fn all_chat_names(&self) -> Vec<String> {
         self.chats.iter().map(|ch| ch.name.to_string()).collect()
     }
 
+    fn broadcast_prompt(&mut self, frontend: &FrontendComms) {
+        let prompt = std::mem::take(&mut self.broadcast.input);
+        let mut busy = vec![];
+        let mut unsent = vec![];
+        for name in self.broadcast.targets.iter() {
+            if let Some(chat) = self.chats.iter_mut().find(|ch| &ch.name == name) {
+                match chat.processing_response {
+                    true => busy.push(name.to_owned()),
+                    false => {
+                        if chat.submit_prompt(prompt.to_owned(), frontend).is_err() {
+                            unsent.push(name.to_owned());
+                        }
+                    }
+                }
+            }
+        }
+        let mut problems = vec![];
+        if !busy.is_empty() {
+            problems.push(format!("Skipped busy chats: {}", busy.join(", ")));
+        }
+        if !unsent.is_empty() {
+            problems.push(format!("Couldn't send to: {}", unsent.join(", ")));
+        }
+        self.broadcast.error_message = match problems.is_empty() {
+            true => None,
+            false => Some(problems.join(". ")),
+        };
+    }
+
+    fn broadcast_push(&mut self, frontend: &FrontendComms, directory: bool) {
+        let path = match directory {
+            true => pick_directory(),
+            false => pick_file(),
+        };
+        if let Some(path) = path {
+            for name in self.broadcast.targets.iter() {
+                if let Some(chat) = self.chats.iter_mut().find(|ch| &ch.name == name) {
+                    match directory {
+                        true => chat.push_directory(path.to_owned(), frontend),
+                        false => chat.push_file(path.to_owned(), frontend),
+                    }
+                }
+            }
+        }
+    }
+
+    fn display_broadcast_controls(&mut self, frontend: &FrontendComms, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.label(format!(
+            "Broadcast to {} chats",
+            self.broadcast.targets.len()
+        ));
+        ui.add(
+            egui::TextEdit::multiline(&mut self.broadcast.input)
+                .desired_rows(2)
+                .desired_width(f32::INFINITY)
+                .hint_text("Prompt for every selected chat"),
+        );
+        ui.add_enabled_ui(!self.broadcast.targets.is_empty(), |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("⮨").on_hover_text("Send prompt").clicked()
+                    && !self.broadcast.input.trim().is_empty()
+                {
+                    self.broadcast_prompt(frontend);
+                }
+                if ui.button("File").on_hover_text("Push a file").clicked() {
+                    self.broadcast_push(frontend, false);
+                }
+                if ui.button("Dir").on_hover_text("Push a directory").clicked() {
+                    self.broadcast_push(frontend, true);
+                }
+            });
+        });
+        if let Some(err) = &self.broadcast.error_message {
+            ui.colored_label(Color32::RED, err);
+        }
+    }
+
+    /// Files holding a single conversation come straight in, anything bigger asks first
+    fn start_import(&mut self, frontend: &FrontendComms) {
+        let path = match pick_import_file() {
+            Some(path) => path,
+            None => return,
+        };
+        match parse_conversations(&path) {
+            Ok(conversations) if conversations.len() == 1 => {
+                self.import_chats(conversations, frontend);
+            }
+            Ok(conversations) => {
+                self.import = Some(PendingImport {
+                    selected: vec![false; conversations.len()],
+                    conversations,
+                });
+            }
+            Err(err) => {
+                if let Some(chat) = self
+                    .current_chat_name
+                    .to_owned()
+                    .and_then(|name| self.get_chat_by_name(&name))
+                {
+                    chat.error_message = Some(format!("Import failed: {}", err));
+                }
+                tracing::warn!("Failed to import {}: {:?}", path.display(), err);
+            }
+        }
+    }
+
+    /// Writes each conversation's display to disk so `Chat::restore` picks it up when the
+    /// backend announces the thread. The whole import is a single command however many
+    /// conversations there are
+    fn import_chats(&mut self, conversations: Vec<ImportedChat>, frontend: &FrontendComms) {
+        let mut taken_names = self.all_chat_names();
+        let mut imported = vec![];
+        for conversation in conversations.into_iter() {
+            let mut name = conversation.name.to_owned();
+            let mut n = 2;
+            while taken_names.contains(&name) {
+                name = format!("{} ({})", conversation.name, n);
+                n += 1;
+            }
+            taken_names.push(name.to_owned());
+
+            let display = SavedDisplay {
+                name: name.to_owned(),
+                messages: conversation.messages,
+                meta: conversation.meta,
+            };
+            if let Err(err) = save_display(&display) {
+                tracing::warn!("Failed to save imported {} chat: {:?}", name, err);
+            }
+            let messages = persistence::message_vector(&display.messages);
+            imported.push(ImportedThread {
+                name,
+                config: conversation.config.unwrap_or_default(),
+                history: messages.as_ref().to_owned(),
+            });
+        }
+        if imported.is_empty() {
+            return;
+        }
+        let names: Vec<String> = imported.iter().map(|chat| chat.name.to_owned()).collect();
+        if let Err(err) = frontend.try_send(BackendCommand::ImportChats { chats: imported }) {
+            for name in names.iter() {
+                if let Err(err) = remove_chat(name) {
+                    tracing::warn!("Failed to clean up imported {} chat: {:?}", name, err);
+                }
+            }
+            if let Some(chat) = self
+                .current_chat_name
+                .to_owned()
+                .and_then(|name| self.get_chat_by_name(&name))
+            {
+                chat.error_message = Some(format!("Import failed: {}", err));
+            }
+        }
+    }
+
+    fn display_import_modal(&mut self, frontend: &FrontendComms, ctx: &egui::Context) {
+        let mut finished = false;
+        let mut chosen = vec![];
+        if let Some(pending) = &mut self.import {
+            egui::Window::new("Import chats")
+                .collapsible(false)
+                .anchor(Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("All").clicked() {
+                            pending.selected.iter_mut().for_each(|s| *s = true);
+                        }
+                        if ui.small_button("None").clicked() {
+                            pending.selected.iter_mut().for_each(|s| *s = false);
+                        }
+                    });
+                    egui::ScrollArea::vertical()
+                        .max_height(400.0)
+                        .show(ui, |ui| {
+                            for (conversation, selected) in pending
+                                .conversations
+                                .iter()
+                                .zip(pending.selected.iter_mut())
+                            {
+                                ui.checkbox(
+                                    selected,
+                                    format!(
+                                        "{} ({} messages)",
+                                        conversation.name,
+                                        conversation.messages.len()
+                                    ),
+                                );
+                            }
+                        });
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let any_selected = pending.selected.contains(&true);
+                        if ui
+                            .add_enabled(any_selected, egui::Button::new("Import"))
+                            .clicked()
+                        {
+                            chosen = pending
+                                .conversations
+                                .iter()
+                                .zip(pending.selected.iter())
+                                .filter(|(_, selected)| **selected)
+                                .map(|(conversation, _)| conversation.to_owned())
+                                .collect();
+                            finished = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            finished = true;
+                        }
+                    });
+                });
+        }
+        if finished {
+            self.import = None;
+            self.import_chats(chosen, frontend);
+        }
+    }
+
     pub fn display_current_chat(&mut self, frontend: &FrontendComms, outer_ui: &mut egui::Ui) {
         let open_modal = self.create_new_chat_modal_open;
         if open_modal {
             self.display_new_chat_modal(outer_ui, frontend);
         }
-
-        self.listen_for_chat_updates(frontend, outer_ui.ctx());
+        if self.import.is_some() {
+            self.display_import_modal(frontend, outer_ui.ctx());
+        }
 
         let chat_names = self.all_chat_names().clone();
 
@@ -167,24 +803,55 @@ impl ChatPage {
                     false => "➕",
                 };
 
+                self.broadcast
+                    .targets
+                    .retain(|target| chat_names.contains(target));
+                if ui
+                    .selectable_label(self.broadcast.enabled, "📢 Broadcast")
+                    .clicked()
+                {
+                    self.broadcast.enabled = !self.broadcast.enabled;
+                }
+                ui.add_space(6.0);
+
                 for name in chat_names.iter() {
                     let is_selected = Some(name.to_string()) == self.current_chat_name;
                     ui.horizontal(|ui| {
+                        if self.broadcast.enabled {
+                            let mut targeted = self.broadcast.targets.contains(name);
+                            if ui.checkbox(&mut targeted, "").changed() {
+                                match targeted {
+                                    true => self.broadcast.targets.push(name.to_owned()),
+                                    false => self.broadcast.targets.retain(|n| n != name),
+                                }
+                            }
+                        }
                         let chat_selector =
                             ui.radio(is_selected, name.to_string()).context_menu(|ui| {
-                                ui.set_width(1.0);
-                                if chat_names.len() > 1 {
-                                    if ui.button("❌").clicked() {
-                                        let chat_to_remove_name = name.to_string();
-                                        let remove_command = BackendCommand::RemoveChatThread {
-                                            name: chat_to_remove_name.to_owned(),
-                                        };
-                                        frontend.sender.try_send(remove_command).unwrap();
-                                        if Some(chat_to_remove_name) == self.current_chat_name {
-                                            self.current_chat_name = Some(chat_names[0].to_owned())
-                                        }
-                                        self.chats.retain(|ch| &ch.name != name)
+                                if let Some(chat) =
+                                    self.chats.iter_mut().find(|ch| &ch.name == name)
+                                {
+                                    ui.menu_button("Retries", |ui| {
+                                        chat.retry_policy_menu(frontend, ui);
+                                    });
+                                    ui.menu_button("Recall", |ui| {
+                                        chat.recall_menu(frontend, ui);
+                                    });
+                                    ui.menu_button("Export…", |ui| {
+                                        chat.export_menu(ui);
+                                    });
+                                }
+                                if ui.button("❌").clicked() {
+                                    let chat_to_remove_name = name.to_string();
+                                    let remove_command = BackendCommand::RemoveChatThread {
+                                        name: chat_to_remove_name.to_owned(),
+                                    };
+                                    frontend.sender.try_send(remove_command).unwrap();
+                                    if Some(chat_to_remove_name) == self.current_chat_name {
+                                        self.current_chat_name =
+                                            chat_names.iter().find(|n| *n != name).cloned();
                                     }
+                                    self.chats.retain(|ch| &ch.name != name)
                                 }
                             });
 
@@ -192,6 +859,16 @@ impl ChatPage {
                             let new_chat_name = name.to_string();
                             self.current_chat_name = Some(new_chat_name);
                         }
+                        if let Some(chat) = self.chats.iter().find(|ch| &ch.name == name) {
+                            if chat.processing_response {
+                                ui.spinner();
+                            } else if let Some(err) = &chat.error_message {
+                                ui.colored_label(Color32::RED, "⚠").on_hover_text(err);
+                            }
+                            if chat.ltm_offline {
+                                chat.ltm_offline_label(ui);
+                            }
+                        }
                         // FUTURE FEATURE: CHANGING AGENTS
                         // if ui.small_button("≡").clicked() {
                         //     // Method for returning agent reference from backend command??
@@ -210,13 +887,37 @@ impl ChatPage {
                 }
 
                 ui.add_space(10.0);
-                if ui.button(add_button_value).clicked() {
-                    self.create_new_chat_modal_open = !self.create_new_chat_modal_open;
+                ui.horizontal(|ui| {
+                    if ui.button(add_button_value).clicked() {
+                        self.create_new_chat_modal_open = !self.create_new_chat_modal_open;
+                    }
+                    if ui.button("📥").on_hover_text("Import chat").clicked() {
+                        self.start_import(frontend);
+                    }
+                });
+
+                if self.broadcast.enabled {
+                    self.display_broadcast_controls(frontend, ui);
                 }
             });
-        let current_chat_name = &self.current_chat_name.to_owned().unwrap();
-        let chat = self.get_chat_by_name(current_chat_name).unwrap();
-        chat.display(frontend, outer_ui);
+        // Startup can be configured with no chats at all
+        let current_chat = self
+            .current_chat_name
+            .to_owned()
+            .and_then(|name| self.get_chat_by_name(&name));
+        match current_chat {
+            Some(chat) => chat.display(frontend, outer_ui),
+            None => {
+                CentralPanel::default().show(outer_ui.ctx(), |ui| {
+                    ui.centered_and_justified(|ui| {
+                        ui.colored_label(
+                            Color32::GRAY,
+                            "No chats yet, ➕ to create one or 📥 to import one",
+                        );
+                    });
+                });
+            }
+        }
     }
 }
 
@@ -226,8 +927,187 @@ impl Chat {
             name: name.to_string(),
             processing_response: false,
             chat_buffer: MessageVector::init(),
+            message_meta: vec![],
             current_exchange: CurrentExchange::default(),
             error_message: None,
+            status_message: None,
+            retry_override: None,
+            recall: RecallConfig::default(),
+            has_long_term_thread: false,
+            last_prompt: None,
+            stalled: false,
+            ltm_offline: false,
+            related: RelatedContext::default(),
+            remember: None,
+            inspector: MemoryInspector::default(),
+            journal: display_journal(name),
+            unjournaled_tokens: String::new(),
+            tokens_journaled_at: Instant::now(),
+            scroll_to_message: None,
+            highlighted_message: None,
+        }
+    }
+
+    /// Picks up whatever was displayed for this chat last time the app ran, including
+    /// anything that only made it into the journal before a crash. `config` comes from the
+    /// backend, a chat created this session hasn't been saved yet
+    pub fn restore(name: &str, config: &AgentConfig, frontend: &FrontendComms) -> Self {
+        let mut chat = Self::init(name);
+        chat.recall = config.recall.clone();
+        chat.has_long_term_thread = config.long_term_thread.is_some();
+        if let Some(recovered) = load_display(name) {
+            let saved = recovered.display;
+            chat.chat_buffer = persistence::message_vector(&saved.messages);
+            chat.message_meta = saved.meta;
+            chat.message_meta
+                .resize_with(chat.chat_buffer.len(), MessageMeta::default);
+            // The agent only hears about a response once it's finished streaming
+            if let Some(interrupted) = recovered.interrupted {
+                let started = !interrupted.content.is_empty();
+                if started {
+                    frontend
+                        .sender
+                        .try_send(BackendCommand::PushToAgentMemory {
+                            agent_name: name.to_string(),
+                            message: Message::from(&interrupted),
+                        })
+                        .unwrap();
+                }
+                chat.stalled = true;
+                chat.last_prompt = saved
+                    .messages
+                    .iter()
+                    .rev()
+                    .find(|message| message.role == "user")
+                    .map(|message| message.content.to_owned());
+                chat.error_message = Some(match started {
+                    true => "Response was interrupted".to_string(),
+                    false => "Response was interrupted before it started".to_string(),
+                });
+            }
+            chat.compact();
+        }
+        if let Some(saved) = load_agent(name) {
+            chat.retry_override = saved.retry_override;
+        }
+        chat
+    }
+
+    /// Only reopens the journal once per `TOKEN_JOURNAL_INTERVAL`, not for every token
+    fn journal_token(&mut self, token: &str) {
+        self.unjournaled_tokens.push_str(token);
+        if self.tokens_journaled_at.elapsed() >= TOKEN_JOURNAL_INTERVAL {
+            self.journal.append(&DisplayEntry::StreamToken {
+                token: std::mem::take(&mut self.unjournaled_tokens),
+            });
+            self.tokens_journaled_at = Instant::now();
+        }
+    }
+
+    /// Anything else ends or restarts the stream, or replaces it with the full message,
+    /// so tokens still waiting to be journaled are no longer needed
+    fn journal_entry(&mut self, entry: &DisplayEntry) {
+        self.unjournaled_tokens.clear();
+        self.tokens_journaled_at = Instant::now();
+        self.journal.append(entry);
+    }
+
+    fn push_message(&mut self, message: Message, meta: MessageMeta) {
+        self.journal_entry(&DisplayEntry::Message {
+            message: (&message).into(),
+            meta: meta.clone(),
+        });
+        self.chat_buffer.as_mut().push(message);
+        self.message_meta.push(meta);
+    }
+
+    pub fn message_meta(&self) -> &[MessageMeta] {
+        &self.message_meta
+    }
+
+    /// Folds the journal into a fresh snapshot
+    fn compact(&mut self) {
+        let saved = SavedDisplay {
+            name: self.name.to_string(),
+            messages: persistence::saved_messages(&self.chat_buffer),
+            meta: self.message_meta.clone(),
+        };
+        match save_display(&saved) {
+            Ok(_) => self.journal.truncate(),
+            Err(err) => tracing::warn!("Failed to save {} chat: {:?}", self.name, err),
+        }
+    }
+
+    /// Never mid-stream, the snapshot doesn't hold the tokens streamed so far
+    fn compact_if_needed(&mut self) {
+        if self.journal.needs_compaction() && self.current_exchange.stream_buffer.is_none() {
+            self.compact();
+        }
+    }
+
+    pub fn transcript(&self) -> ChatTranscript {
+        // The backend keeps the config, what it last saved is as current as the display
+        let config = load_agent(&self.name).map(|saved| saved.config);
+        ChatTranscript::new(
+            &self.name,
+            None,
+            config,
+            &self.chat_buffer,
+            &self.message_meta,
+        )
+    }
+
+    fn export_menu(&mut self, ui: &mut egui::Ui) {
+        for format in ExportFormat::all() {
+            if ui.button(format.to_string()).clicked() {
+                ui.close_menu();
+                let file_name = format!("{}.{}", persistence::slug(&self.name), format.extension());
+                if let Some(path) = rfd::FileDialog::new()
+                    .set_file_name(&file_name)
+                    .add_filter(&format.to_string(), &[format.extension()])
+                    .save_file()
+                {
+                    if let Err(err) = self.transcript().write(format, &path) {
+                        self.error_message = Some(format!("Export failed: {}", err));
+                    }
+                }
+            }
+        }
+    }
+
+    fn retry_policy_menu(&mut self, frontend: &FrontendComms, ui: &mut egui::Ui) {
+        let mut use_global = self.retry_override.is_none();
+        if ui.checkbox(&mut use_global, "Use global policy").changed() {
+            self.retry_override = match use_global {
+                true => None,
+                false => Some(RetryPolicy::default()),
+            };
+        }
+        if let Some(policy) = &mut self.retry_override {
+            retry_policy_form(ui, policy);
+        }
+        if ui.button("💾").clicked() {
+            frontend
+                .sender
+                .try_send(BackendCommand::SetChatRetryPolicy {
+                    agent_name: self.name.to_string(),
+                    policy: self.retry_override.clone(),
+                })
+                .unwrap();
+            ui.close_menu();
+        }
+    }
+
+    /// Applied as soon as a mode is picked
+    fn recall_menu(&mut self, frontend: &FrontendComms, ui: &mut egui::Ui) {
+        if recall_mode_options(ui, &mut self.recall, self.has_long_term_thread) {
+            frontend
+                .sender
+                .try_send(BackendCommand::SetChatRecall {
+                    agent_name: self.name.to_string(),
+                    recall: self.recall.clone(),
+                })
+                .unwrap();
         }
     }
 
@@ -236,8 +1116,24 @@ impl Chat {
         let chat_width = ui.available_width();
         let chat_height = ui.available_height();
         let font_size = 16.0;
+        let has_long_term_thread = self.has_long_term_thread;
+        let mut remember = None;
 
-        for message in buffer.into_iter() {
+        for (message_idx, message) in buffer.into_iter().enumerate() {
+            let message_top = ui.cursor().top();
+            if let Some(removed) = self
+                .message_meta
+                .get(message_idx)
+                .and_then(|meta| meta.compacted)
+            {
+                memory_divider(
+                    ui,
+                    message_idx,
+                    removed,
+                    &message.content().unwrap_or_default(),
+                );
+                continue;
+            }
             let content = message.content().unwrap_or(String::new());
             let content = match message.role() {
                 MessageRole::User => format!("👤 {}", content),
@@ -267,9 +1163,52 @@ impl Chat {
                             .frame(false)
                             .font(font)
                             .margin([2.0, 1.0].into()),
-                    );
+                    )
+                    .context_menu(|ui| {
+                        if ui
+                            .add_enabled(has_long_term_thread, egui::Button::new("Remember this…"))
+                            .on_disabled_hover_text("This chat has no long term memory thread")
+                            .clicked()
+                        {
+                            remember = Some(message_idx);
+                            ui.close_menu();
+                        }
+                    });
                 });
             }
+            if self
+                .message_meta
+                .get(message_idx)
+                .map_or(false, |meta| meta.incomplete)
+            {
+                ui.colored_label(Color32::KHAKI, "⚠ incomplete");
+            }
+
+            let message_rect = egui::Rect::from_min_max(
+                egui::pos2(ui.max_rect().left(), message_top),
+                egui::pos2(ui.max_rect().right(), ui.cursor().top()),
+            );
+            if self.scroll_to_message == Some(message_idx) {
+                ui.scroll_to_rect(message_rect, Some(egui::Align::Center));
+            }
+            if let Some((idx, since)) = self.highlighted_message {
+                if idx == message_idx && since.elapsed() < Duration::from_secs(3) {
+                    ui.painter().rect_stroke(
+                        message_rect.expand(2.0),
+                        4.0,
+                        egui::Stroke::new(1.5, Color32::GOLD),
+                    );
+                }
+            }
+        }
+        self.scroll_to_message = None;
+
+        if let Some(message) = remember.and_then(|idx| buffer.get(idx)) {
+            let original = SavedMessage::from(message);
+            self.remember = Some(RememberDraft {
+                text: original.content.to_owned(),
+                original,
+            });
         }
 
         if let Some(current_stream_buffer) = &mut self.current_exchange.stream_buffer {
@@ -295,6 +1234,7 @@ impl Chat {
             .movable(false)
             .title_bar(false)
             .show(outer_ui.ctx(), |ui| {
+                self.display_related_context(frontend, ui);
                 ui.horizontal(|ui| {
                     let user_input_box =
                         egui::TextEdit::multiline(&mut self.current_exchange.user_input)
@@ -308,8 +1248,30 @@ impl Chat {
                         ui.colored_label(Color32::RED, error_message.as_ref().unwrap());
                     }
 
+                    if let Some(status) = &self.status_message {
+                        ui.colored_label(Color32::KHAKI, status);
+                    }
+
+                    if self.ltm_offline {
+                        self.ltm_offline_label(ui);
+                    }
+
+                    if ui
+                        .selectable_label(self.inspector.open, "🔍")
+                        .on_hover_text("Inspect agent memory")
+                        .clicked()
+                    {
+                        self.inspector.open = !self.inspector.open;
+                    }
+
                     let user_input_handle = ui.add(user_input_box);
 
+                    if self.stalled && !self.processing_response {
+                        if ui.button("↻ Retry").clicked() {
+                            self.retry_last_prompt(frontend, outer_ui.ctx());
+                        }
+                    }
+
                     let enter_button = egui::Button::new("⮨");
                     let enter_button_handle = match self.processing_response {
                         true => ui.spinner(),
@@ -318,62 +1280,14 @@ impl Chat {
                             .on_hover_text("Right click for more options")
                             .context_menu(|ui| {
                                 if ui.button("Add File").clicked() {
-                                    if let Some(path) = rfd::FileDialog::new()
-                                        .add_filter("plaintext", &["txt", "md"])
-                                        .add_filter(
-                                            "code",
-                                            &["rs", "toml", "yaml", "py", "js", "ts", "c", "json"],
-                                        )
-                                        .set_directory("/")
-                                        .pick_file()
-                                    {
-                                        let path_string = path.display().to_string();
-                                        let file = File::from(path);
-                                        // file.get_summary().await
-                                        frontend
-                                            .sender
-                                            .try_send(BackendCommand::PushToAgentMemory {
-                                                agent_name: self.name.to_string(),
-                                                message: file.to_message(),
-                                            })
-                                            .unwrap();
-                                        let response_content =
-                                            format!("Pushed file: {} to Agent memory", path_string);
-
-                                        self.chat_buffer.push(
-                                            response_content
-                                                .to_message_with_role(MessageRole::System),
-                                        )
+                                    if let Some(path) = pick_file() {
+                                        self.push_file(path, frontend);
                                     }
                                 }
 
                                 if ui.button("Add Directory").clicked() {
-                                    if let Some(path) =
-                                        rfd::FileDialog::new().set_directory("/").pick_folder()
-                                    {
-                                        let path_string = path.display().to_string();
-                                        let directory = Directory::from(path);
-                                        let messages: MessageVector = directory.into();
-
-                                        for message in messages.as_ref().to_owned().into_iter() {
-                                            frontend
-                                                .sender
-                                                .try_send(BackendCommand::PushToAgentMemory {
-                                                    agent_name: self.name.to_string(),
-                                                    message,
-                                                })
-                                                .unwrap();
-                                        }
-
-                                        let response_content = format!(
-                                            "Pushed directory: {} to Agent memory",
-                                            path_string
-                                        );
-
-                                        self.chat_buffer.push(
-                                            response_content
-                                                .to_message_with_role(MessageRole::System),
-                                        )
+                                    if let Some(path) = pick_directory() {
+                                        self.push_directory(path, frontend);
                                     }
                                 }
                             }),
@@ -399,19 +1313,24 @@ impl Chat {
                             }
                             false => {
                                 scroll_to_bottom = true;
-                                self.chat_buffer.as_mut().push(
-                                    self.current_exchange
-                                        .user_input
-                                        .to_message_with_role(MessageRole::User),
-                                );
-                                self.send_last_user_message_to_backend(frontend, outer_ui.ctx());
-                                self.processing_response = true;
+                                outer_ui.ctx().request_repaint();
+                                let prompt = std::mem::take(&mut self.current_exchange.user_input);
+                                if self.submit_prompt(prompt.to_owned(), frontend).is_err() {
+                                    self.current_exchange.user_input = prompt;
+                                }
                             }
                         }
                     }
                 });
             });
 
+        self.look_up_related_context(frontend);
+        self.display_remember_window(frontend, outer_ui.ctx());
+        if self.inspector.open {
+            self.refresh_inspector(frontend);
+            self.display_memory_inspector(frontend, outer_ui.ctx());
+        }
+
         CentralPanel::default().show(outer_ui.ctx(), |ui| {
             let chat_width = ui.available_size().x * 0.95;
             let chat_height = ui.available_size().y * 0.95;
@@ -433,17 +1352,424 @@ impl Chat {
         });
     }
 
-    fn send_last_user_message_to_backend(&mut self, frontend: &FrontendComms, ctx: &egui::Context) {
-        ctx.request_repaint();
-        let backend_command = BackendCommand::StreamedCompletion {
-            agent_name: self.name.to_owned(),
-            prompt: self.current_exchange.user_input.to_owned(),
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_processing(&self) -> bool {
+        self.processing_response
+    }
+
+    pub fn stats(&self) -> &ExchangeStats {
+        &self.current_exchange.stats
+    }
+
+    fn ltm_offline_label(&self, ui: &mut egui::Ui) {
+        ui.colored_label(Color32::KHAKI, "LTM offline")
+            .on_hover_text(
+                "The long term memory database is unreachable. This chat only has short term \
+             memory for now, what it would have saved is queued until the database is back",
+            );
+    }
+
+    /// Every message with its index in the display, leaving out the memory dividers that
+    /// share the buffer with them
+    pub fn conversation(&self) -> impl Iterator<Item = (usize, &Message)> {
+        self.chat_buffer
+            .as_ref()
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| {
+                self.message_meta
+                    .get(*idx)
+                    .map_or(true, |meta| meta.compacted.is_none())
+            })
+    }
+
+    /// Where the next message pushed onto the display will go
+    pub fn message_count(&self) -> usize {
+        self.chat_buffer.as_ref().len()
+    }
+
+    /// What's streaming right now, or the last thing the assistant said from `start` on
+    pub fn response_since(&self, start: usize) -> Option<String> {
+        if let Some(buffer) = &self.current_exchange.stream_buffer {
+            return Some(buffer.to_owned());
+        }
+        self.chat_buffer
+            .as_ref()
+            .iter()
+            .skip(start)
+            .last()
+            .filter(|message| message.role() == MessageRole::Assistant)
+            .and_then(|message| message.content())
+    }
+
+    pub fn error_message(&self) -> Option<&str> {
+        self.error_message.as_deref()
+    }
+
+    pub fn stream_buffer(&self) -> Option<&str> {
+        self.current_exchange.stream_buffer.as_deref()
+    }
+
+    /// What's streaming right now, or the last thing the assistant said
+    pub fn latest_response(&self) -> Option<String> {
+        if let Some(buffer) = &self.current_exchange.stream_buffer {
+            return Some(buffer.to_owned());
+        }
+        self.chat_buffer
+            .as_ref()
+            .last()
+            .filter(|message| message.role() == MessageRole::Assistant)
+            .and_then(|message| message.content())
+    }
+
+    pub fn push_file(&mut self, path: PathBuf, frontend: &FrontendComms) {
+        let path_string = path.display().to_string();
+        let file = File::from(path);
+        // file.get_summary().await
+        let response_content = format!("Pushed file: {} to Agent memory", path_string);
+        self.push_to_agent_memory(vec![file.to_message()], response_content, frontend);
+    }
+
+    pub fn push_directory(&mut self, path: PathBuf, frontend: &FrontendComms) {
+        let path_string = path.display().to_string();
+        let directory = Directory::from(path);
+        let messages: MessageVector = directory.into();
+        let response_content = format!("Pushed directory: {} to Agent memory", path_string);
+        self.push_to_agent_memory(messages.as_ref().to_owned(), response_content, frontend);
+    }
+
+    /// One command however many files there are, so a big directory can't fill the channel
+    fn push_to_agent_memory(
+        &mut self,
+        messages: Vec<Message>,
+        response_content: String,
+        frontend: &FrontendComms,
+    ) {
+        let command = BackendCommand::PushHistoryToAgentMemory {
+            agent_name: self.name.to_string(),
+            messages,
         };
-        self.current_exchange.user_input.clear();
+        if let Err(err) = frontend.try_send(command) {
+            self.error_message = Some(format!("Push failed: {}", err));
+            return;
+        }
+        self.push_message(
+            response_content.to_message_with_role(MessageRole::System),
+            MessageMeta::now(),
+        );
+        self.compact_if_needed();
+    }
+
+    /// Keeps a single request in flight, the chat thread only answers between completions
+    fn refresh_inspector(&mut self, frontend: &FrontendComms) {
+        let stale = self
+            .inspector
+            .refreshed_at
+            .map_or(true, |at| at.elapsed() >= INSPECTOR_REFRESH);
+        if self.inspector.awaiting || !stale {
+            return;
+        }
+        self.inspector.awaiting = true;
+        frontend
+            .sender
+            .try_send(BackendCommand::InspectAgentMemory {
+                agent_name: self.name.to_string(),
+            })
+            .unwrap();
+    }
+
+    fn display_memory_inspector(&mut self, frontend: &FrontendComms, ctx: &egui::Context) {
+        let mut pin = None;
+        SidePanel::new(egui::panel::Side::Right, "AgentMemoryPanel")
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.heading("Agent memory");
+                ui.colored_label(
+                    Color32::GRAY,
+                    "Sent ahead of the next prompt, token counts are estimates",
+                );
+                if self.inspector.messages.is_empty() && self.inspector.awaiting {
+                    ui.spinner();
+                    return;
+                }
+                let total_tokens: usize = self.inspector.messages.iter().map(|m| m.tokens).sum();
+                ui.label(format!(
+                    "{} messages, ~{} tokens",
+                    self.inspector.messages.len(),
+                    total_tokens
+                ));
+                if !self.inspector.pins_apply {
+                    ui.colored_label(
+                        Color32::GRAY,
+                        "Pins only apply with the TokenBudget caching mechanism",
+                    );
+                }
+                ui.separator();
+                let pins_apply = self.inspector.pins_apply;
+                egui::ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .show(ui, |ui| {
+                        for (idx, message) in self.inspector.messages.iter_mut().enumerate() {
+                            let color = match message.origin {
+                                MessageOrigin::InitPrompt => Color32::GOLD,
+                                MessageOrigin::Push => Color32::LIGHT_BLUE,
+                                MessageOrigin::Summary => Color32::KHAKI,
+                                MessageOrigin::Conversation => Color32::LIGHT_GRAY,
+                            };
+                            egui::CollapsingHeader::new(
+                                RichText::new(format!(
+                                    "{}{}. {} · {} · ~{} tokens",
+                                    if message.pinned { "📌 " } else { "" },
+                                    idx + 1,
+                                    message.role,
+                                    message.origin,
+                                    message.tokens
+                                ))
+                                .color(color),
+                            )
+                            .id_source(("inspected_message", idx))
+                            .show(ui, |ui| {
+                                ui.horizontal(|ui| {
+                                    ui.colored_label(
+                                        Color32::GRAY,
+                                        format!("{} chars", message.chars),
+                                    );
+                                    // Unpinning stays possible after switching away
+                                    // from a token budget
+                                    if ui
+                                        .add_enabled(
+                                            pins_apply || message.pinned,
+                                            egui::SelectableLabel::new(message.pinned, "📌 Pin"),
+                                        )
+                                        .on_hover_text("Never forgotten by the token budget")
+                                        .on_disabled_hover_text(
+                                            "Switch the chat to the TokenBudget caching mechanism to pin messages",
+                                        )
+                                        .clicked()
+                                    {
+                                        message.pinned = !message.pinned;
+                                        pin = Some(message.clone());
+                                    }
+                                });
+                                let preview: String = message
+                                    .content
+                                    .chars()
+                                    .take(INSPECTOR_PREVIEW_CHARS)
+                                    .collect();
+                                match message.chars > INSPECTOR_PREVIEW_CHARS {
+                                    true => ui.label(format!("{}…", preview)),
+                                    false => ui.label(preview),
+                                };
+                            });
+                        }
+                    });
+            });
+        if let Some(message) = pin {
+            frontend
+                .sender
+                .try_send(BackendCommand::SetPinnedMessage {
+                    agent_name: self.name.to_string(),
+                    message: SavedMessage {
+                        role: message.role,
+                        content: message.content,
+                    },
+                    pinned: message.pinned,
+                })
+                .unwrap();
+            self.inspector.outdated = self.inspector.awaiting;
+            self.inspector.refreshed_at = None;
+        }
+    }
 
+    /// Saves straight to the chat's long term thread, without waiting on the caching
+    /// mechanism to summarize
+    fn display_remember_window(&mut self, frontend: &FrontendComms, ctx: &egui::Context) {
+        let mut open = self.remember.is_some();
+        let mut close = false;
+        if let Some(draft) = &mut self.remember {
+            egui::Window::new("Remember this")
+                .open(&mut open)
+                .collapsible(false)
+                .show(ctx, |ui| {
+                    ui.label("Save it as is, or edit it down to a summary");
+                    ui.add(
+                        egui::TextEdit::multiline(&mut draft.text)
+                            .desired_rows(6)
+                            .desired_width(f32::INFINITY),
+                    );
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(
+                                !draft.text.trim().is_empty(),
+                                egui::Button::new("Save to LTM"),
+                            )
+                            .clicked()
+                        {
+                            frontend
+                                .sender
+                                .try_send(BackendCommand::RememberInLongTerm {
+                                    agent_name: self.name.to_string(),
+                                    message: draft.to_saved_message(),
+                                })
+                                .unwrap();
+                            close = true;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            close = true;
+                        }
+                    });
+                });
+        }
+        if !open || close {
+            self.remember = None;
+        }
+    }
+
+    /// Asks the backend for related context once typing has paused on something long
+    /// enough to be worth searching for
+    fn look_up_related_context(&mut self, frontend: &FrontendComms) {
+        let input = self.current_exchange.user_input.trim();
+        let related = &mut self.related;
+        if input != related.watched_input {
+            related.watched_input = input.to_string();
+            related.changed_at = Some(Instant::now());
+            return;
+        }
+        let paused = related
+            .changed_at
+            .map_or(false, |at| at.elapsed() >= RELATED_CONTEXT_DELAY);
+        if !paused || input.chars().count() < RELATED_CONTEXT_MIN_CHARS {
+            return;
+        }
+        related.changed_at = None;
+        if related.requested.as_deref() == Some(input) {
+            return;
+        }
+        related.requested = Some(input.to_string());
         frontend
             .sender
-            .try_send(backend_command)
-            .expect("Failed to send user input to backend");
+            .try_send(BackendCommand::FindRelatedContext {
+                agent_name: self.name.to_string(),
+                query: input.to_string(),
+            })
+            .unwrap();
+    }
+
+    fn accept_related_snippet(&mut self, idx: usize, frontend: &FrontendComms) {
+        let snippet = match self.related.snippets.get(idx) {
+            Some(snippet) => snippet.source.to_owned(),
+            None => return,
+        };
+        let content = format!("Related context from {}:\n{}", snippet.source, snippet.text);
+        frontend
+            .sender
+            .try_send(BackendCommand::PushToAgentMemory {
+                agent_name: self.name.to_string(),
+                message: content.to_message_with_role(MessageRole::System),
+            })
+            .unwrap();
+        self.related.accepted.push(idx);
+        self.push_message(
+            format!(
+                "Pushed related context from {} to Agent memory",
+                snippet.source
+            )
+            .to_message_with_role(MessageRole::System),
+            MessageMeta::now(),
+        );
+        self.compact_if_needed();
+    }
+
+    fn display_related_context(&mut self, frontend: &FrontendComms, ui: &mut egui::Ui) {
+        if self.related.snippets.is_empty() {
+            return;
+        }
+        let mut accept = None;
+        egui::CollapsingHeader::new(
+            RichText::new(format!("Related context ({})", self.related.snippets.len()))
+                .color(Color32::LIGHT_BLUE),
+        )
+        .id_source("related_context")
+        .show(ui, |ui| {
+            for (idx, snippet) in self.related.snippets.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    match self.related.accepted.contains(&idx) {
+                        true => {
+                            ui.colored_label(Color32::GREEN, "✔");
+                        }
+                        false => {
+                            if ui
+                                .small_button("➕")
+                                .on_hover_text("Add to the agent's memory")
+                                .clicked()
+                            {
+                                accept = Some(idx);
+                            }
+                        }
+                    }
+                    ui.colored_label(Color32::LIGHT_BLUE, &snippet.source.source);
+                    ui.colored_label(Color32::GOLD, &snippet.source.role);
+                    ui.colored_label(Color32::GRAY, format!("{:.0}%", snippet.score * 100.0));
+                });
+                let preview: String = snippet.source.text.chars().take(160).collect();
+                ui.label(preview.replace('\n', " "))
+                    .on_hover_text(&snippet.source.text);
+            }
+        });
+        if let Some(idx) = accept {
+            self.accept_related_snippet(idx, frontend);
+        }
+    }
+
+    /// Sends the prompt off for completion and pushes it onto the displayed chat. If the
+    /// backend can't take it nothing changes apart from the error shown on this chat
+    pub fn submit_prompt(
+        &mut self,
+        prompt: String,
+        frontend: &FrontendComms,
+    ) -> anyhow::Result<()> {
+        let backend_command = BackendCommand::StreamedCompletion {
+            agent_name: self.name.to_owned(),
+            prompt: prompt.to_owned(),
+        };
+        if let Err(err) = frontend.try_send(backend_command) {
+            self.error_message = Some(format!("Prompt wasn't sent: {}", err));
+            return Err(err);
+        }
+        self.error_message = None;
+        self.related.snippets.clear();
+        self.push_message(
+            prompt.to_message_with_role(MessageRole::User),
+            MessageMeta::now(),
+        );
+        self.last_prompt = Some(prompt);
+        self.stalled = false;
+        self.processing_response = true;
+        self.current_exchange.stats = ExchangeStats::started_now();
+        self.compact_if_needed();
+        self.journal_entry(&DisplayEntry::StreamStarted);
+        Ok(())
+    }
+
+    /// The backend still has the stalled exchange in memory, it swaps it for the retry
+    fn retry_last_prompt(&mut self, frontend: &FrontendComms, ctx: &egui::Context) {
+        if self.last_prompt.is_some() {
+            ctx.request_repaint();
+            let command = BackendCommand::RetryLastPrompt {
+                agent_name: self.name.to_owned(),
+            };
+            if let Err(err) = frontend.try_send(command) {
+                self.error_message = Some(format!("Retry wasn't sent: {}", err));
+                return;
+            }
+            self.stalled = false;
+            self.error_message = None;
+            self.processing_response = true;
+            self.current_exchange.stats = ExchangeStats::started_now();
+            self.journal_entry(&DisplayEntry::StreamStarted);
+        }
     }
 }
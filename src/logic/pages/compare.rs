@@ -0,0 +1,177 @@
+use super::chat::{ChatPage, ExchangeStats};
+use crate::logic::{backend::inspector::estimate_tokens, comms::FrontendComms};
+use eframe::{
+    egui::{self, RichText},
+    epaint::{Color32, FontId},
+};
+
+/// Sends one prompt to several chats and lays their responses out side by side
+#[derive(Debug)]
+pub struct ComparePage {
+    prompt: String,
+    selected_chats: Vec<String>,
+    /// Each chat in the current round with where its response starts in the chat, so a
+    /// column never shows a reply from an earlier round
+    columns: Vec<(String, usize)>,
+    error_message: Option<String>,
+}
+
+impl ComparePage {
+    pub fn init() -> Self {
+        Self {
+            prompt: String::new(),
+            selected_chats: vec![],
+            columns: vec![],
+            error_message: None,
+        }
+    }
+
+    fn chat_selection(&mut self, chat_page: &ChatPage, ui: &mut egui::Ui) {
+        let chat_names = chat_page.all_chat_names();
+        self.selected_chats.retain(|name| chat_names.contains(name));
+        self.columns.retain(|(name, _)| chat_names.contains(name));
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Compare:");
+            for name in chat_names.iter() {
+                let mut selected = self.selected_chats.contains(name);
+                if ui.checkbox(&mut selected, name).changed() {
+                    match selected {
+                        true => self.selected_chats.push(name.to_owned()),
+                        false => self.selected_chats.retain(|n| n != name),
+                    }
+                }
+            }
+        });
+    }
+
+    fn send_prompt(&mut self, chat_page: &mut ChatPage, frontend: &FrontendComms) {
+        if self.selected_chats.is_empty() {
+            self.error_message = Some("Select at least one chat".to_string());
+            return;
+        }
+        let busy: Vec<String> = self
+            .selected_chats
+            .iter()
+            .filter(|name| chat_page.chat(name).map_or(false, |ch| ch.is_processing()))
+            .cloned()
+            .collect();
+        if !busy.is_empty() {
+            self.error_message = Some(format!("Still responding: {}", busy.join(", ")));
+            return;
+        }
+
+        let prompt = std::mem::take(&mut self.prompt);
+        let mut unsent = vec![];
+        self.columns.clear();
+        for name in self.selected_chats.iter() {
+            if let Some(chat) = chat_page.chat_mut(name) {
+                match chat.submit_prompt(prompt.to_owned(), frontend) {
+                    Ok(_) => self.columns.push((name.to_owned(), chat.message_count())),
+                    Err(_) => unsent.push(name.to_owned()),
+                }
+            }
+        }
+        self.error_message = match unsent.is_empty() {
+            true => None,
+            false => Some(format!("Couldn't send to: {}", unsent.join(", "))),
+        };
+    }
+
+    fn stats_line(stats: &ExchangeStats, response: &str) -> String {
+        let ttft = match stats.time_to_first_token() {
+            Some(ttft) => format!("{:.2}s", ttft.as_secs_f32()),
+            None => "–".to_string(),
+        };
+        let total = match stats.total_time() {
+            Some(total) => format!("{:.2}s", total.as_secs_f32()),
+            None => "–".to_string(),
+        };
+        format!(
+            "first token: {} · total: {} · ~{} tokens",
+            ttft,
+            total,
+            estimate_tokens(response)
+        )
+    }
+
+    fn display_columns(&self, chat_page: &ChatPage, ui: &mut egui::Ui) {
+        if self.columns.is_empty() {
+            return;
+        }
+        ui.columns(self.columns.len(), |columns| {
+            for (column, (name, start)) in columns.iter_mut().zip(self.columns.iter()) {
+                let chat = match chat_page.chat(name) {
+                    Some(chat) => chat,
+                    None => continue,
+                };
+                column.horizontal(|ui| {
+                    ui.colored_label(
+                        Color32::LIGHT_BLUE,
+                        RichText::new(name)
+                            .font(FontId::proportional(16.0))
+                            .strong(),
+                    );
+                    if chat.is_processing() {
+                        ui.spinner();
+                    }
+                });
+                column.separator();
+                let mut response = chat.response_since(*start).unwrap_or_default();
+                egui::ScrollArea::vertical()
+                    .id_source(format!("compare_{}", name))
+                    .max_height(column.available_height() - 40.0)
+                    .auto_shrink([false, true])
+                    .stick_to_bottom(true)
+                    .show(column, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut response)
+                                .desired_width(f32::INFINITY)
+                                .text_color(Color32::from_rgb(210, 220, 255))
+                                .frame(false)
+                                .font(FontId::proportional(14.0))
+                                .interactive(false),
+                        );
+                    });
+                column.separator();
+                if let Some(err) = chat.error_message().filter(|_| !chat.is_processing()) {
+                    column.colored_label(Color32::RED, err);
+                }
+                column.colored_label(Color32::GOLD, Self::stats_line(chat.stats(), &response));
+            }
+        });
+    }
+
+    pub fn display(
+        &mut self,
+        chat_page: &mut ChatPage,
+        frontend: &FrontendComms,
+        ui: &mut egui::Ui,
+    ) {
+        self.chat_selection(chat_page, ui);
+
+        ui.horizontal(|ui| {
+            let prompt_box = egui::TextEdit::multiline(&mut self.prompt)
+                .desired_rows(2)
+                .hint_text("Prompt to send to every selected chat");
+            ui.add(prompt_box);
+            if ui.button("Compare").clicked() && !self.prompt.trim().is_empty() {
+                self.send_prompt(chat_page, frontend);
+            }
+        });
+        if let Some(err) = &self.error_message {
+            ui.colored_label(Color32::RED, err);
+        }
+        ui.separator();
+
+        self.display_columns(chat_page, ui);
+
+        let any_processing = self
+            .columns
+            .iter()
+            .any(|(name, _)| chat_page.chat(name).map_or(false, |ch| ch.is_processing()));
+        if any_processing {
+            ui.ctx().request_repaint();
+        }
+    }
+}
@@ -0,0 +1,328 @@
+use super::chat::{Chat, ChatPage};
+use crate::logic::comms::FrontendComms;
+use eframe::{
+    egui::{self, RichText},
+    epaint::{Color32, FontId},
+};
+use espionox::memory::MessageRole;
+use std::time::Duration;
+
+/// How often a running dialogue is checked on while nothing else repaints the app
+const RUN_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Runs an automated conversation between two existing chats, feeding each
+/// assistant reply to the other chat as a user prompt
+#[derive(Debug)]
+pub struct DialoguePage {
+    speakers: [Option<String>; 2],
+    opening_prompt: String,
+    max_turns: u32,
+    stop_phrase: String,
+    run: Option<DialogueRun>,
+    error_message: Option<String>,
+}
+
+#[derive(Debug)]
+struct DialogueRun {
+    speakers: [String; 2],
+    max_turns: u32,
+    stop_phrase: Option<String>,
+    turns_taken: u32,
+    waiting_on: usize,
+    awaiting_response: bool,
+    pending_reply: Option<String>,
+    step_requested: bool,
+    status: RunStatus,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RunStatus {
+    Running,
+    Paused,
+    Finished(String),
+}
+
+impl DialogueRun {
+    fn other(&self) -> usize {
+        (self.waiting_on + 1) % 2
+    }
+
+    fn finish(&mut self, reason: impl ToString) {
+        self.status = RunStatus::Finished(reason.to_string());
+        self.pending_reply = None;
+    }
+
+    /// Checks whether the current speaker is done and forwards its reply when allowed
+    fn advance(&mut self, chat_page: &mut ChatPage, frontend: &FrontendComms) {
+        if let RunStatus::Finished(_) = self.status {
+            return;
+        }
+        let speaker = self.speakers[self.waiting_on].to_owned();
+        let chat = match chat_page.chat(&speaker) {
+            Some(chat) => chat,
+            None => return self.finish(format!("{} was removed", speaker)),
+        };
+
+        if self.awaiting_response && !chat.is_processing() {
+            self.awaiting_response = false;
+            self.turns_taken += 1;
+            let reply = match chat.latest_response() {
+                Some(reply) => reply,
+                None => return self.finish(format!("{} didn't respond", speaker)),
+            };
+            if let Some(phrase) = &self.stop_phrase {
+                if reply.contains(phrase.as_str()) {
+                    return self.finish(format!("{} said \"{}\"", speaker, phrase));
+                }
+            }
+            if self.turns_taken >= self.max_turns {
+                return self.finish(format!("Reached {} turns", self.max_turns));
+            }
+            self.pending_reply = Some(reply);
+        }
+
+        let may_forward = match self.status {
+            RunStatus::Running => true,
+            RunStatus::Paused => self.step_requested,
+            RunStatus::Finished(_) => false,
+        };
+        if may_forward && self.pending_reply.is_some() {
+            let listener = self.speakers[self.other()].to_owned();
+            let reply = self.pending_reply.take().unwrap();
+            match chat_page.chat_mut(&listener) {
+                Some(chat) if !chat.is_processing() => {
+                    if let Err(err) = chat.submit_prompt(reply, frontend) {
+                        self.finish(format!("Couldn't send to {}: {}", listener, err));
+                        return;
+                    }
+                    self.waiting_on = self.other();
+                    self.awaiting_response = true;
+                    self.step_requested = false;
+                }
+                Some(_) => self.pending_reply = Some(reply),
+                None => self.finish(format!("{} was removed", listener)),
+            }
+        }
+    }
+}
+
+impl DialoguePage {
+    pub fn init() -> Self {
+        Self {
+            speakers: [None, None],
+            opening_prompt: String::new(),
+            max_turns: 10,
+            stop_phrase: String::new(),
+            run: None,
+            error_message: None,
+        }
+    }
+
+    fn start(&mut self, chat_page: &mut ChatPage, frontend: &FrontendComms) {
+        let speakers = match &self.speakers {
+            [Some(first), Some(second)] if first != second => [first.to_owned(), second.to_owned()],
+            _ => {
+                self.error_message = Some("Pick two different chats".to_string());
+                return;
+            }
+        };
+        if self.opening_prompt.trim().is_empty() {
+            self.error_message = Some("Opening prompt cannot be empty".to_string());
+            return;
+        }
+        let first = match chat_page.chat_mut(&speakers[0]) {
+            Some(chat) if !chat.is_processing() => chat,
+            _ => {
+                self.error_message = Some(format!("{} is busy", speakers[0]));
+                return;
+            }
+        };
+        if let Err(err) = first.submit_prompt(self.opening_prompt.to_owned(), frontend) {
+            self.error_message = Some(format!("Couldn't start: {}", err));
+            return;
+        }
+
+        let stop_phrase = match self.stop_phrase.trim().is_empty() {
+            true => None,
+            false => Some(self.stop_phrase.trim().to_string()),
+        };
+        self.run = Some(DialogueRun {
+            speakers,
+            max_turns: self.max_turns,
+            stop_phrase,
+            turns_taken: 0,
+            waiting_on: 0,
+            awaiting_response: true,
+            pending_reply: None,
+            step_requested: false,
+            status: RunStatus::Running,
+        });
+        self.error_message = None;
+    }
+
+    fn setup_form(
+        &mut self,
+        chat_page: &mut ChatPage,
+        frontend: &FrontendComms,
+        ui: &mut egui::Ui,
+    ) {
+        let chat_names = chat_page.all_chat_names();
+        ui.horizontal(|ui| {
+            for (i, speaker) in self.speakers.iter_mut().enumerate() {
+                if speaker
+                    .as_ref()
+                    .map_or(false, |name| !chat_names.contains(name))
+                {
+                    *speaker = None;
+                }
+                egui::ComboBox::from_id_source(format!("dialogue_speaker_{}", i))
+                    .selected_text(speaker.clone().unwrap_or("Pick a chat".to_string()))
+                    .show_ui(ui, |ui| {
+                        for name in chat_names.iter() {
+                            ui.selectable_value(speaker, Some(name.to_owned()), name);
+                        }
+                    });
+                if i == 0 {
+                    ui.label("⇄");
+                }
+            }
+        });
+        ui.add(
+            egui::TextEdit::multiline(&mut self.opening_prompt)
+                .desired_rows(2)
+                .hint_text("Opening prompt for the first chat"),
+        );
+        ui.horizontal(|ui| {
+            ui.add(egui::Slider::new(&mut self.max_turns, 1..=50).text("Turns"));
+            ui.add(egui::TextEdit::singleline(&mut self.stop_phrase).hint_text("Stop phrase"));
+        });
+        if ui.button("Start").clicked() {
+            self.start(chat_page, frontend);
+        }
+        if let Some(err) = &self.error_message {
+            ui.colored_label(Color32::RED, err);
+        }
+    }
+
+    fn run_controls(&mut self, ui: &mut egui::Ui) {
+        let mut clear_run = false;
+        if let Some(run) = &mut self.run {
+            ui.horizontal(|ui| {
+                match run.status.clone() {
+                    RunStatus::Running => {
+                        if ui.button("⏸ Pause").clicked() {
+                            run.status = RunStatus::Paused;
+                        }
+                    }
+                    RunStatus::Paused => {
+                        if ui.button("▶ Resume").clicked() {
+                            run.status = RunStatus::Running;
+                        }
+                        if ui.button("⏭ Step").clicked() {
+                            run.step_requested = true;
+                        }
+                    }
+                    RunStatus::Finished(_) => {
+                        if ui.button("New dialogue").clicked() {
+                            clear_run = true;
+                        }
+                    }
+                }
+                if let RunStatus::Running | RunStatus::Paused = run.status {
+                    if ui.button("⏹ Stop").clicked() {
+                        run.finish("Stopped");
+                    }
+                }
+
+                let status = match &run.status {
+                    RunStatus::Finished(reason) => format!("Finished: {}", reason),
+                    _ => format!(
+                        "Turn {}/{} · waiting on {}",
+                        run.turns_taken + 1,
+                        run.max_turns,
+                        run.speakers[run.waiting_on]
+                    ),
+                };
+                ui.colored_label(Color32::GOLD, status);
+            });
+        }
+        if clear_run {
+            self.run = None;
+        }
+    }
+
+    fn transcript(chat: &Chat, ui: &mut egui::Ui) {
+        for (_, message) in chat.conversation() {
+            let content = message.content().unwrap_or_default();
+            let (prefix, color) = match message.role() {
+                MessageRole::User => ("👤", Color32::from_rgb(255, 223, 223)),
+                MessageRole::Assistant => ("🕵", Color32::from_rgb(210, 220, 255)),
+                _ => ("💻", Color32::from_rgb(255, 224, 230)),
+            };
+            ui.colored_label(color, format!("{} {}", prefix, content));
+            ui.add_space(4.0);
+        }
+        if let Some(buffer) = chat.stream_buffer() {
+            ui.colored_label(Color32::from_rgb(210, 220, 255), format!("🕵 {}", buffer));
+        }
+    }
+
+    fn transcripts(&self, chat_page: &ChatPage, ui: &mut egui::Ui) {
+        let run = match &self.run {
+            Some(run) => run,
+            None => return,
+        };
+        ui.columns(2, |columns| {
+            for (column, name) in columns.iter_mut().zip(run.speakers.iter()) {
+                column.colored_label(
+                    Color32::LIGHT_BLUE,
+                    RichText::new(name)
+                        .font(FontId::proportional(16.0))
+                        .strong(),
+                );
+                column.separator();
+                if let Some(chat) = chat_page.chat(name) {
+                    egui::ScrollArea::vertical()
+                        .id_source(format!("dialogue_{}", name))
+                        .auto_shrink([false; 2])
+                        .stick_to_bottom(true)
+                        .show(column, |ui| Self::transcript(chat, ui));
+                }
+            }
+        });
+    }
+
+    /// Called every frame whichever page is open, so a dialogue keeps going while the
+    /// user is elsewhere
+    pub fn advance(
+        &mut self,
+        chat_page: &mut ChatPage,
+        frontend: &FrontendComms,
+        ctx: &egui::Context,
+    ) {
+        if let Some(run) = &mut self.run {
+            run.advance(chat_page, frontend);
+            if run.status == RunStatus::Running {
+                ctx.request_repaint_after(RUN_POLL_INTERVAL);
+            }
+        }
+    }
+
+    pub fn display(
+        &mut self,
+        chat_page: &mut ChatPage,
+        frontend: &FrontendComms,
+        ui: &mut egui::Ui,
+    ) {
+        match &self.run {
+            None => self.setup_form(chat_page, frontend, ui),
+            Some(_) => self.run_controls(ui),
+        }
+        ui.separator();
+        self.transcripts(chat_page, ui);
+
+        if self.run.is_some() {
+            ui.ctx().request_repaint();
+        }
+    }
+}
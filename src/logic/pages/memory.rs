@@ -0,0 +1,270 @@
+use crate::logic::{
+    backend::long_term::LtmEntry,
+    comms::{BackendCommand, FrontendComms},
+};
+use chrono::Local;
+use eframe::{
+    egui::{self, CentralPanel, RichText, SidePanel},
+    epaint::{Color32, FontId},
+};
+
+const PREVIEW_CHARS: usize = 200;
+
+/// Audit what long term memory has stored, thread by thread
+#[derive(Debug, Default)]
+pub struct MemoryPage {
+    /// `None` until the backend answers
+    threads: Option<Result<Vec<String>, String>>,
+    selected: Option<String>,
+    entries: Option<Result<Vec<LtmEntry>, String>>,
+    search: String,
+    /// Entries shown in full rather than as a preview
+    expanded: Vec<String>,
+    confirm_wipe: bool,
+    requested: bool,
+}
+
+impl MemoryPage {
+    pub fn init() -> Self {
+        Self::default()
+    }
+
+    pub fn threads_loaded(&mut self, threads: Result<Vec<String>, String>) {
+        if let (Some(selected), Ok(threads)) = (&self.selected, &threads) {
+            if !threads.contains(selected) {
+                self.selected = None;
+                self.entries = None;
+            }
+        }
+        self.threads = Some(threads);
+    }
+
+    pub fn entries_loaded(&mut self, thread: String, entries: Result<Vec<LtmEntry>, String>) {
+        if self.selected.as_ref() == Some(&thread) {
+            self.entries = Some(entries);
+        }
+    }
+
+    fn send(frontend: &FrontendComms, command: BackendCommand) {
+        frontend.sender.try_send(command).unwrap();
+    }
+
+    fn refresh(&mut self, frontend: &FrontendComms) {
+        self.threads = None;
+        Self::send(frontend, BackendCommand::ListLtmThreads);
+        if let Some(thread) = &self.selected {
+            self.entries = None;
+            Self::send(
+                frontend,
+                BackendCommand::LoadLtmThread {
+                    thread: thread.to_owned(),
+                },
+            );
+        }
+    }
+
+    fn select(&mut self, frontend: &FrontendComms, thread: String) {
+        self.entries = None;
+        self.expanded.clear();
+        self.confirm_wipe = false;
+        Self::send(
+            frontend,
+            BackendCommand::LoadLtmThread {
+                thread: thread.to_owned(),
+            },
+        );
+        self.selected = Some(thread);
+    }
+
+    fn matches_search(&self, entry: &LtmEntry) -> bool {
+        let search = self.search.trim().to_lowercase();
+        search.is_empty()
+            || entry.content.to_lowercase().contains(&search)
+            || entry.role.to_lowercase().contains(&search)
+    }
+
+    fn display_threads(&mut self, frontend: &FrontendComms, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Threads");
+            if ui.small_button("🔄").on_hover_text("Reload").clicked() {
+                self.refresh(frontend);
+            }
+        });
+        ui.separator();
+        let threads = match &self.threads {
+            None => {
+                ui.spinner();
+                return;
+            }
+            Some(Err(err)) => {
+                ui.colored_label(Color32::RED, err);
+                return;
+            }
+            Some(Ok(threads)) if threads.is_empty() => {
+                ui.label("Nothing stored yet");
+                return;
+            }
+            Some(Ok(threads)) => threads.to_owned(),
+        };
+        egui::ScrollArea::vertical()
+            .id_source("ltm_threads")
+            .show(ui, |ui| {
+                for thread in threads.into_iter() {
+                    let is_selected = self.selected.as_ref() == Some(&thread);
+                    if ui.selectable_label(is_selected, &thread).clicked() && !is_selected {
+                        self.select(frontend, thread);
+                    }
+                }
+            });
+    }
+
+    fn display_entry(&mut self, frontend: &FrontendComms, entry: &LtmEntry, ui: &mut egui::Ui) {
+        let expanded = self.expanded.contains(&entry.id);
+        ui.horizontal(|ui| {
+            match (entry.readable, entry.is_summary()) {
+                (false, _) => ui
+                    .colored_label(Color32::RED, "unreadable")
+                    .on_hover_text("This row doesn't have the fields espionox writes"),
+                (true, true) => ui.colored_label(Color32::LIGHT_BLUE, "summary"),
+                (true, false) => ui.colored_label(Color32::GOLD, &entry.role),
+            };
+            if let Some(created_at) = entry.created_at {
+                ui.colored_label(
+                    Color32::GRAY,
+                    created_at
+                        .with_timezone(&Local)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string(),
+                );
+            }
+            let toggle = match expanded {
+                true => "Collapse",
+                false => "View",
+            };
+            if ui.small_button(toggle).clicked() {
+                match expanded {
+                    true => self.expanded.retain(|id| id != &entry.id),
+                    false => self.expanded.push(entry.id.to_owned()),
+                }
+            }
+            if ui
+                .add_enabled(!entry.id.is_empty(), egui::Button::new("🗑").small())
+                .on_hover_text("Delete entry")
+                .on_disabled_hover_text("This row has no id to delete it by")
+                .clicked()
+            {
+                Self::send(
+                    frontend,
+                    BackendCommand::DeleteLtmEntry {
+                        thread: self.selected.to_owned().unwrap_or_default(),
+                        id: entry.id.to_owned(),
+                    },
+                );
+            }
+        });
+        let mut content = match expanded || entry.content.chars().count() <= PREVIEW_CHARS {
+            true => entry.content.to_owned(),
+            false => format!(
+                "{}…",
+                entry
+                    .content
+                    .chars()
+                    .take(PREVIEW_CHARS)
+                    .collect::<String>()
+            ),
+        };
+        ui.add(
+            egui::TextEdit::multiline(&mut content)
+                .desired_width(f32::INFINITY)
+                .frame(false)
+                .interactive(expanded),
+        );
+    }
+
+    fn display_selected(&mut self, frontend: &FrontendComms, ui: &mut egui::Ui) {
+        let thread = match &self.selected {
+            Some(thread) => thread.to_owned(),
+            None => {
+                ui.label("Select a thread");
+                return;
+            }
+        };
+        ui.horizontal(|ui| {
+            ui.colored_label(
+                Color32::LIGHT_BLUE,
+                RichText::new(&thread)
+                    .font(FontId::proportional(18.0))
+                    .strong(),
+            );
+            ui.add_space(10.0);
+            match self.confirm_wipe {
+                false => {
+                    if ui.button("Wipe thread").clicked() {
+                        self.confirm_wipe = true;
+                    }
+                }
+                true => {
+                    ui.colored_label(Color32::RED, "Delete everything in this thread?");
+                    if ui.button("Wipe").clicked() {
+                        self.confirm_wipe = false;
+                        self.entries = None;
+                        Self::send(
+                            frontend,
+                            BackendCommand::WipeLtmThread {
+                                thread: thread.to_owned(),
+                            },
+                        );
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.confirm_wipe = false;
+                    }
+                }
+            }
+        });
+        ui.add(egui::TextEdit::singleline(&mut self.search).hint_text("🔍 Search entries"));
+        ui.separator();
+
+        let entries: Vec<LtmEntry> = match &self.entries {
+            None => {
+                ui.spinner();
+                return;
+            }
+            Some(Err(err)) => {
+                ui.colored_label(Color32::RED, err);
+                return;
+            }
+            Some(Ok(entries)) => entries
+                .iter()
+                .filter(|entry| self.matches_search(entry))
+                .cloned()
+                .collect(),
+        };
+        ui.label(format!("{} entries", entries.len()));
+        egui::ScrollArea::vertical()
+            .id_source("ltm_entries")
+            .auto_shrink([false; 2])
+            .show(ui, |ui| {
+                for entry in entries.iter() {
+                    ui.separator();
+                    self.display_entry(frontend, entry, ui);
+                }
+            });
+    }
+
+    pub fn display(&mut self, frontend: &FrontendComms, ui: &mut egui::Ui) {
+        if !self.requested {
+            self.requested = true;
+            self.refresh(frontend);
+        }
+
+        SidePanel::new(egui::panel::Side::Left, "MemoryPanel")
+            .resizable(true)
+            .show(ui.ctx(), |ui| {
+                self.display_threads(frontend, ui);
+            });
+
+        CentralPanel::default().show(ui.ctx(), |ui| {
+            self.display_selected(frontend, ui);
+        });
+    }
+}
@@ -1,8 +1,18 @@
 pub mod chat;
+pub mod compare;
+pub mod dialogue;
+pub mod memory;
 pub mod modals;
+pub mod prompts;
+pub mod search;
 pub mod settings;
 
 pub use chat::ChatPage;
+pub use compare::ComparePage;
+pub use dialogue::DialoguePage;
+pub use memory::MemoryPage;
+pub use prompts::PromptsPage;
+pub use search::GlobalSearch;
 pub use settings::SettingsPage;
 
 use eframe::egui;
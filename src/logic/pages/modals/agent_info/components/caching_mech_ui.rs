@@ -1,3 +1,4 @@
+use crate::logic::backend::config::{CachingConfig, DEFAULT_TOKEN_BUDGET};
 use eframe::{
     egui::{self, RichText},
     epaint::{Color32, FontId},
@@ -7,89 +8,129 @@ use espionox::memory::CachingMechanism;
 #[derive(Debug)]
 pub struct CachingMechanismUi {
     options_open: bool,
-    mech: CachingMechanism,
+    mech: CachingConfig,
     limit: f32,
+    max_tokens: f32,
     long_term_enabled: bool,
-    mech_replacement: Option<CachingMechanism>,
+    /// Whether the chat has a long term thread to save to
+    long_term_available: bool,
+    mech_replacement: Option<CachingConfig>,
 }
 
-impl From<CachingMechanism> for CachingMechanismUi {
-    fn from(value: CachingMechanism) -> Self {
-        let mech = value;
-        let limit = mech.limit() as f32;
-        let long_term_enabled = match mech {
-            CachingMechanism::SummarizeAtLimit { save_to_lt, .. } => save_to_lt,
-            _ => false,
-        };
-        Self {
+impl From<CachingConfig> for CachingMechanismUi {
+    fn from(value: CachingConfig) -> Self {
+        let mut ui = Self {
             options_open: false,
-            mech,
-            limit,
-            long_term_enabled,
-            mech_replacement: None,
-        }
+            mech: CachingConfig::Forgetful,
+            limit: CachingMechanism::default_summary_at_limit().limit() as f32,
+            max_tokens: DEFAULT_TOKEN_BUDGET as f32,
+            long_term_enabled: false,
+            long_term_available: false,
+            mech_replacement: Some(value),
+        };
+        ui.change_to_replacement();
+        ui
     }
 }
 
 impl CachingMechanismUi {
     pub fn mech_name(&self) -> String {
-        String::from(match &self.mech {
-            CachingMechanism::Forgetful => "Forgetful",
-            CachingMechanism::SummarizeAtLimit { .. } => "SummarizeAtLimit",
-        })
+        self.mech.name().to_string()
     }
 
-    pub fn caching_mechanism(&self) -> &CachingMechanism {
+    pub fn caching_config(&self) -> &CachingConfig {
         &self.mech
     }
 
+    pub fn set_long_term_available(&mut self, available: bool) {
+        self.long_term_available = available;
+    }
+
     fn change_to_replacement(&mut self) {
         if let Some(new_mech) = self.mech_replacement.take() {
             let mech = new_mech;
-            let limit = mech.limit() as f32;
-            let long_term_enabled = mech.long_term_enabled();
-
+            self.long_term_enabled = false;
+            match mech {
+                CachingConfig::Forgetful => {
+                    self.limit = CachingMechanism::Forgetful.limit() as f32;
+                }
+                CachingConfig::SummarizeAtLimit { limit, save_to_lt } => {
+                    self.limit = limit as f32;
+                    self.long_term_enabled = save_to_lt;
+                }
+                CachingConfig::TokenBudget { max_tokens } => {
+                    self.max_tokens = max_tokens as f32;
+                }
+            }
             self.mech = mech;
-            self.limit = limit;
-            self.long_term_enabled = long_term_enabled;
         }
     }
 
     fn options_display(&mut self, ui: &mut egui::Ui) {
         ui.indent("CachingOptions", |ui| {
             if let None = self.mech_replacement {
-                self.mech_replacement = Some(CachingMechanism::Forgetful);
+                self.mech_replacement = Some(match self.mech {
+                    CachingConfig::Forgetful => CachingConfig::Forgetful,
+                    CachingConfig::SummarizeAtLimit { .. } => {
+                        CachingConfig::default_summary_at_limit()
+                    }
+                    CachingConfig::TokenBudget { .. } => CachingConfig::default_token_budget(),
+                });
             }
             ui.radio_value(
                 &mut self.mech_replacement,
-                Some(CachingMechanism::Forgetful),
+                Some(CachingConfig::Forgetful),
                 "Forgetful",
             );
             ui.radio_value(
                 &mut self.mech_replacement,
-                Some(CachingMechanism::default_summary_at_limit()),
+                Some(CachingConfig::default_summary_at_limit()),
                 "SummarizeAtLimit",
             );
-            if self.mech_replacement != Some(CachingMechanism::Forgetful) {
-                let upper_bounds = 100.0;
-                let lower_bounds = 10.0;
-                ui.add(
-                    egui::Slider::new(&mut self.limit, lower_bounds..=upper_bounds)
-                        .text("Cache size limit"),
-                );
-                ui.checkbox(&mut self.long_term_enabled, "Save to LTM");
+            ui.radio_value(
+                &mut self.mech_replacement,
+                Some(CachingConfig::default_token_budget()),
+                "TokenBudget",
+            );
+            match self.mech_replacement {
+                Some(CachingConfig::SummarizeAtLimit { .. }) => {
+                    let upper_bounds = 100.0;
+                    let lower_bounds = 10.0;
+                    ui.add(
+                        egui::Slider::new(&mut self.limit, lower_bounds..=upper_bounds)
+                            .text("Cache size limit"),
+                    );
+                    ui.add_enabled(
+                        self.long_term_available,
+                        egui::Checkbox::new(&mut self.long_term_enabled, "Save to LTM"),
+                    )
+                    .on_disabled_hover_text("Enable Long Term Memory for this chat first");
+                    ui.label("Pinned messages get summarized like the rest");
+                }
+                Some(CachingConfig::TokenBudget { .. }) => {
+                    ui.add(
+                        egui::Slider::new(&mut self.max_tokens, 500.0..=128000.0)
+                            .logarithmic(true)
+                            .text("Max tokens"),
+                    );
+                    ui.label("Pin messages from the memory inspector to keep them");
+                }
+                _ => {}
             }
 
             if ui.button("💾").clicked() {
                 let mech = &self.mech_replacement;
                 self.mech_replacement = match mech {
-                    Some(CachingMechanism::Forgetful) => Some(CachingMechanism::Forgetful),
-                    Some(CachingMechanism::SummarizeAtLimit { .. }) => {
-                        Some(CachingMechanism::SummarizeAtLimit {
+                    Some(CachingConfig::Forgetful) => Some(CachingConfig::Forgetful),
+                    Some(CachingConfig::SummarizeAtLimit { .. }) => {
+                        Some(CachingConfig::SummarizeAtLimit {
                             limit: self.limit as usize,
                             save_to_lt: self.long_term_enabled,
                         })
                     }
+                    Some(CachingConfig::TokenBudget { .. }) => Some(CachingConfig::TokenBudget {
+                        max_tokens: self.max_tokens as usize,
+                    }),
                     None => None,
                 };
                 self.change_to_replacement();
@@ -100,12 +141,18 @@ impl CachingMechanismUi {
 
     pub fn overview_display(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
-            ui.colored_label(
-                Color32::GOLD,
-                format!("Caching limit: {}", &self.mech.limit()),
-            );
+            let limit = match self.mech {
+                CachingConfig::TokenBudget { max_tokens } => {
+                    format!("Token budget: {}", max_tokens)
+                }
+                _ => format!(
+                    "Caching limit: {}",
+                    CachingMechanism::from(&self.mech).limit()
+                ),
+            };
+            ui.colored_label(Color32::GOLD, limit);
 
-            if self.long_term_enabled {
+            if self.long_term_enabled && self.long_term_available {
                 ui.colored_label(
                     Color32::GOLD,
                     RichText::new("LTM").font(FontId::proportional(9.0)),
@@ -1,14 +1,26 @@
-use eframe::egui::{self, Button, Layout};
-use espionox::{
-    memory::{Message, MessageRole, MessageVector},
-    persistance::prompts::{get_prompts_from_file, Prompt},
+use crate::logic::persistence::prompts::{
+    load_prompts, prompts_file, save_prompt_as, update_prompt, PromptSaveError, PromptsWatcher,
+    SavedPrompt,
 };
+use eframe::{
+    egui::{self, Button, Layout},
+    epaint::Color32,
+};
+use espionox::memory::{Message, MessageRole, MessageVector};
 use std::{cell::RefCell, rc::Rc};
 
 #[derive(Debug)]
 pub struct InitPromptUi {
-    loaded_prompts: Option<Vec<Prompt>>,
+    /// `Err` when the prompts file couldn't be read
+    loaded_prompts: Result<Vec<SavedPrompt>, String>,
+    prompts_watcher: PromptsWatcher,
     show_loaded_prompts: bool,
+    /// The prompt as it was read from disk, `Update` writes over it
+    loaded_from: Option<SavedPrompt>,
+    show_save_as: bool,
+    save_as_name: String,
+    save_status: Option<Result<String, String>>,
+    update_conflict: bool,
     messages: MessageVector,
     current_message_idx: usize,
     current_message_content: String,
@@ -26,13 +38,16 @@ impl From<MessageVector> for InitPromptUi {
             .unwrap()
             .to_string();
         let current_message_role = value.as_ref()[current_message_idx].role();
-        let loaded_prompts = match get_prompts_from_file() {
-            Ok(p) => Some(p),
-            Err(_) => None,
-        };
+        let loaded_prompts = load_prompts().map_err(|err| err.to_string());
         Self {
             loaded_prompts,
+            prompts_watcher: PromptsWatcher::new(),
             show_loaded_prompts: false,
+            loaded_from: None,
+            show_save_as: false,
+            save_as_name: String::new(),
+            save_status: None,
+            update_conflict: false,
             messages: value,
             current_message_idx,
             current_message_content,
@@ -46,6 +61,12 @@ impl InitPromptUi {
         &self.messages
     }
 
+    /// Like `init_prompt`, but includes whatever is still in the message being edited
+    pub fn finished_prompt(&mut self) -> MessageVector {
+        self.save_current();
+        self.messages.clone()
+    }
+
     fn save_current(&mut self) {
         let current = Message::new_standard(
             self.current_message_role.to_owned(),
@@ -89,22 +110,131 @@ impl InitPromptUi {
             .is_some()
     }
 
-    fn mutate_with_prompt(&mut self, prompt: Prompt) {
-        let messages: MessageVector = prompt.messages.into();
-        *self = Self::from(messages);
+    fn mutate_with_prompt(&mut self, prompt: SavedPrompt) {
+        *self = Self::from(prompt.message_vector());
+        self.loaded_from = Some(prompt);
+    }
+
+    fn reload_prompts(&mut self) {
+        self.loaded_prompts = load_prompts().map_err(|err| err.to_string());
+    }
+
+    fn save_as(&mut self) {
+        self.save_current();
+        let name = self.save_as_name.trim().to_string();
+        let prompt = SavedPrompt::new(&name, &self.messages);
+        self.save_status = Some(match save_prompt_as(prompt.to_owned()) {
+            Ok(_) => {
+                self.loaded_from = Some(prompt);
+                self.show_save_as = false;
+                self.save_as_name.clear();
+                self.reload_prompts();
+                Ok(format!("Saved '{}'", name))
+            }
+            Err(err) => Err(err.to_string()),
+        });
+    }
+
+    fn update(&mut self, force: bool) {
+        self.save_current();
+        let original = match &self.loaded_from {
+            Some(original) => original.to_owned(),
+            None => return,
+        };
+        let updated = SavedPrompt {
+            messages: SavedPrompt::new(&original.name, &self.messages).messages,
+            ..original.to_owned()
+        };
+        self.update_conflict = false;
+        self.save_status = Some(match update_prompt(&original, updated.to_owned(), force) {
+            Ok(_) => {
+                self.loaded_from = Some(updated);
+                self.reload_prompts();
+                Ok(format!("Updated '{}'", original.name))
+            }
+            Err(err) => {
+                self.update_conflict = matches!(err, PromptSaveError::Conflict(_));
+                Err(err.to_string())
+            }
+        });
+    }
+
+    fn display_save_controls(&mut self, ui: &mut egui::Ui) {
+        if self.show_save_as {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.save_as_name)
+                        .hint_text("Prompt name")
+                        .desired_width(150.0),
+                );
+                let can_save = !self.save_as_name.trim().is_empty();
+                if ui
+                    .add_enabled(can_save, Button::new("💾").small())
+                    .clicked()
+                {
+                    self.save_as();
+                }
+            });
+        }
+        match &self.save_status {
+            Some(Ok(status)) => {
+                ui.colored_label(Color32::LIGHT_GREEN, status);
+            }
+            Some(Err(err)) => {
+                ui.colored_label(Color32::RED, err);
+            }
+            None => {}
+        }
+        if self.update_conflict {
+            ui.horizontal(|ui| {
+                if ui.small_button("Overwrite anyway").clicked() {
+                    self.update(true);
+                }
+                if ui.small_button("Reload from disk").clicked() {
+                    let name = self.loaded_from.as_ref().map(|p| p.name.to_owned());
+                    let prompts = match load_prompts() {
+                        Ok(prompts) => prompts,
+                        Err(err) => {
+                            self.save_status = Some(Err(err.to_string()));
+                            return;
+                        }
+                    };
+                    match prompts.into_iter().find(|p| Some(&p.name) == name.as_ref()) {
+                        Some(prompt) => self.mutate_with_prompt(prompt),
+                        None => {
+                            self.loaded_from = None;
+                            self.update_conflict = false;
+                            self.save_status =
+                                Some(Err("Prompt no longer exists on disk".to_string()));
+                        }
+                    }
+                }
+            });
+        }
     }
 
     pub fn display_saved_prompt_options(&mut self, ui: &mut egui::Ui) {
+        if self.prompts_watcher.changed() {
+            self.reload_prompts();
+        }
         ui.with_layout(Layout::top_down(eframe::emath::Align::Min), |ui| {
-            if let Some(vec) = self.loaded_prompts.take() {
-                for p in vec.iter() {
-                    if ui.button(&p.name).clicked() {
-                        self.mutate_with_prompt(p.clone());
-                        self.show_loaded_prompts = false;
-                        break;
+            let mut chosen = None;
+            match &self.loaded_prompts {
+                Ok(prompts) => {
+                    for p in prompts.iter() {
+                        if ui.button(&p.name).clicked() {
+                            chosen = Some(p.clone());
+                            break;
+                        }
                     }
                 }
-                self.loaded_prompts = Some(vec);
+                Err(err) => {
+                    ui.colored_label(Color32::RED, err);
+                }
+            }
+            if let Some(prompt) = chosen {
+                self.mutate_with_prompt(prompt);
+                self.show_loaded_prompts = false;
             }
         });
     }
@@ -124,6 +254,29 @@ impl InitPromptUi {
             if ui.small_button("Load").clicked() {
                 prompt_ui.show_loaded_prompts = !prompt_ui.show_loaded_prompts;
             }
+            if ui
+                .small_button("Save as…")
+                .on_hover_text(format!("Add to {}", prompts_file().display()))
+                .clicked()
+            {
+                prompt_ui.show_save_as = !prompt_ui.show_save_as;
+            }
+            let can_update = prompt_ui.loaded_from.is_some();
+            let update_hover = match &prompt_ui.loaded_from {
+                Some(prompt) => format!(
+                    "Overwrite '{}' in {}",
+                    prompt.name,
+                    prompts_file().display()
+                ),
+                None => "Load a prompt first".to_string(),
+            };
+            if ui
+                .add_enabled(can_update, Button::new("Update").small())
+                .on_hover_text(update_hover)
+                .clicked()
+            {
+                prompt_ui.update(false);
+            }
             ui.add_enabled_ui(*has_last, |ui| {
                 if ui.add(last_button).clicked() {
                     prompt_ui.change_idx(-1);
@@ -196,6 +349,7 @@ impl InitPromptUi {
             if prompt_ui.show_loaded_prompts {
                 prompt_ui.display_saved_prompt_options(ui);
             }
+            prompt_ui.display_save_controls(ui);
         });
     }
 
@@ -1,5 +1,7 @@
 pub(super) mod caching_mech_ui;
 pub(super) mod init_prompt_ui;
+pub(super) mod recall_mode_ui;
 
 pub use caching_mech_ui::*;
 pub use init_prompt_ui::*;
+pub use recall_mode_ui::*;
@@ -0,0 +1,43 @@
+use crate::logic::backend::config::RecallConfig;
+use eframe::{egui, epaint::Color32};
+
+/// Each mode with what it does. These are the unit variants of `RecallMode` at the espionox
+/// rev pinned in `Cargo.lock` (d5cd185), `RecallConfig`'s conversions match it
+/// exhaustively so a variant with parameters, or a new one, stops the build until it's
+/// added here
+const RECALL_MODES: [(RecallConfig, &str, &str); 2] = [
+    (
+        RecallConfig::Manual,
+        "Manual",
+        "Nothing is pulled out of long term memory on its own, the agent only sees its \
+         cache unless something is recalled for it",
+    ),
+    (
+        RecallConfig::Auto,
+        "Auto",
+        "Before each prompt the entries in the chat's long term thread that best match it \
+         are recalled into the agent's context",
+    ),
+];
+
+/// Radio buttons for every recall mode, returns true when the choice changed
+pub fn recall_mode_options(
+    ui: &mut egui::Ui,
+    recall: &mut RecallConfig,
+    long_term_enabled: bool,
+) -> bool {
+    let mut changed = false;
+    for (mode, name, explanation) in RECALL_MODES {
+        changed |= ui.radio_value(recall, mode, name).changed();
+        ui.indent(name, |ui| {
+            ui.colored_label(Color32::GRAY, explanation);
+        });
+    }
+    if !long_term_enabled {
+        ui.colored_label(
+            Color32::KHAKI,
+            "Recall reads from long term memory, which this chat doesn't use",
+        );
+    }
+    changed
+}
@@ -1,17 +1,23 @@
 mod components;
+pub use components::recall_mode_options;
 use components::*;
 
-use crate::logic::{comms::BackendCommand, ChatPage, FrontendComms};
+use crate::logic::{
+    backend::config::{AgentConfig, CachingConfig, RecallConfig},
+    comms::BackendCommand,
+    persistence::{
+        chats::{load_agent, long_term_threads},
+        presets::{load_presets, remove_preset, save_preset, AgentPreset},
+    },
+    ChatPage, FrontendComms,
+};
 use eframe::{
     egui::{self, TextEdit},
     epaint::Color32,
 };
 use espionox::{
     agents::Agent,
-    language_models::LanguageModel,
-    memory::{
-        long_term::LongTermMemory, CachingMechanism, Memory, Message, MessageVector, RecallMode,
-    },
+    memory::{Message, MessageVector},
 };
 use std::{any, cell::RefCell, rc::Rc};
 
@@ -20,9 +26,12 @@ pub struct AgentInfoModal {
     chat_name: String,
     open: OpenOptions,
     init_prompt_ui: Rc<RefCell<InitPromptUi>>,
-    recall_mode: RecallMode,
+    recall: RecallConfig,
     caching_mechanism_ui: CachingMechanismUi,
-    long_term_memory: LongTermMemory,
+    long_term: LongTermOptions,
+    /// `Err` when the presets file couldn't be read
+    presets: Result<Vec<AgentPreset>, String>,
+    preset_name: String,
     pub error_message: Option<String>,
 }
 
@@ -31,6 +40,7 @@ struct OpenOptions {
     system_prompt: bool,
     recall_mode: bool,
     caching_mechanism: bool,
+    long_term_memory: bool,
 }
 
 impl Default for OpenOptions {
@@ -39,6 +49,25 @@ impl Default for OpenOptions {
             system_prompt: false,
             recall_mode: false,
             caching_mechanism: false,
+            long_term_memory: false,
+        }
+    }
+}
+
+/// An empty `thread` falls back to the chat's name
+#[derive(Debug, Default)]
+struct LongTermOptions {
+    enabled: bool,
+    thread: String,
+    known_threads: Vec<String>,
+}
+
+impl LongTermOptions {
+    fn load(thread: Option<String>) -> Self {
+        Self {
+            enabled: thread.is_some(),
+            thread: thread.unwrap_or_default(),
+            known_threads: long_term_threads(),
         }
     }
 }
@@ -51,14 +80,13 @@ impl TryInto<BackendCommand> for &mut AgentInfoModal {
             self.error_message = Some("Name cannot be empty".to_string());
             return Err(anyhow::anyhow!("Name is empty"));
         }
-        let memory = Memory::build()
-            .caching_mechanism(self.caching_mechanism_ui.caching_mechanism().clone())
-            .recall(self.recall_mode.clone())
-            .init_prompt(self.init_prompt_ui.borrow().init_prompt().clone())
-            .finished();
-        let model = LanguageModel::default_gpt();
-        let agent = Agent { memory, model };
-        Ok(BackendCommand::NewChatThread { name, agent })
+        let config = self.agent_config();
+        let init_prompt = self.init_prompt_ui.borrow_mut().finished_prompt();
+        Ok(BackendCommand::NewChatThread {
+            name,
+            config,
+            init_prompt,
+        })
     }
 }
 
@@ -73,9 +101,11 @@ impl AgentInfoModal {
             chat_name: String::new(),
             open: OpenOptions::default(),
             init_prompt_ui,
-            recall_mode: RecallMode::default(),
-            caching_mechanism_ui: CachingMechanism::default().into(),
-            long_term_memory: LongTermMemory::None,
+            recall: RecallConfig::default(),
+            caching_mechanism_ui: CachingConfig::default().into(),
+            long_term: LongTermOptions::load(None),
+            presets: load_presets().map_err(|err| err.to_string()),
+            preset_name: String::new(),
             error_message: None,
         }
     }
@@ -84,17 +114,144 @@ impl AgentInfoModal {
         let mut prompt = agent.memory.cache().clone();
         prompt.reset_to_system_prompt();
         let init_prompt_ui = Rc::new(RefCell::new(prompt.into()));
+        let saved = load_agent(name);
+        // A token budget only shows up in the saved config, the agent itself runs as Forgetful
+        let caching = match &saved {
+            Some(saved) => saved.config.caching.clone(),
+            None => agent.memory.caching_mechanism().into(),
+        };
         Self {
             chat_name: name.to_string(),
             open: OpenOptions::default(),
             init_prompt_ui,
-            recall_mode: agent.memory.recall_mode().clone(),
-            caching_mechanism_ui: agent.memory.caching_mechanism().clone().into(),
-            long_term_memory: LongTermMemory::None,
+            recall: agent.memory.recall_mode().into(),
+            caching_mechanism_ui: caching.into(),
+            long_term: LongTermOptions::load(saved.and_then(|saved| saved.config.long_term_thread)),
+            presets: load_presets().map_err(|err| err.to_string()),
+            preset_name: String::new(),
             error_message: None,
         }
     }
 
+    fn long_term_thread(&self) -> Option<String> {
+        if !self.long_term.enabled {
+            return None;
+        }
+        match self.long_term.thread.trim() {
+            "" => Some(self.chat_name.trim().to_string()),
+            thread => Some(thread.to_string()),
+        }
+    }
+
+    fn agent_config(&self) -> AgentConfig {
+        let mut caching = self.caching_mechanism_ui.caching_config().clone();
+        // Summaries can only be saved somewhere if the chat has a thread
+        if let CachingConfig::SummarizeAtLimit { save_to_lt, .. } = &mut caching {
+            *save_to_lt &= self.long_term.enabled;
+        }
+        AgentConfig {
+            caching,
+            recall: self.recall.clone(),
+            long_term_thread: self.long_term_thread(),
+            pinned: vec![],
+        }
+    }
+
+    /// Fills the form from a preset, the chat name is only touched if it's still empty
+    pub fn apply_preset(&mut self, preset: &AgentPreset) {
+        if self.chat_name.trim().is_empty() {
+            self.chat_name = preset.name.to_owned();
+        }
+        self.init_prompt_ui = Rc::new(RefCell::new(preset.init_prompt().into()));
+        self.recall = preset.config.recall.clone();
+        self.caching_mechanism_ui = preset.config.caching.clone().into();
+        self.long_term = LongTermOptions::load(preset.config.long_term_thread.to_owned());
+    }
+
+    fn save_as_preset(&mut self) {
+        let name = self.preset_name.trim().to_string();
+        let init_prompt = self.init_prompt_ui.borrow_mut().finished_prompt();
+        let preset = AgentPreset::new(&name, &init_prompt, self.agent_config());
+        match save_preset(preset) {
+            Ok(presets) => {
+                self.presets = Ok(presets);
+                self.preset_name.clear();
+                self.error_message = None;
+            }
+            Err(err) => self.error_message = Some(format!("Couldn't save preset: {}", err)),
+        }
+    }
+
+    fn delete_preset(&mut self, name: &str) {
+        match remove_preset(name) {
+            Ok(presets) => {
+                self.presets = Ok(presets);
+                self.error_message = None;
+            }
+            Err(err) => self.error_message = Some(format!("Couldn't remove preset: {}", err)),
+        }
+    }
+
+    fn preset_picker(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let mut chosen = None;
+            let mut removed = None;
+            egui::ComboBox::from_id_source("preset_picker")
+                .selected_text("Preset…")
+                .show_ui(ui, |ui| match &self.presets {
+                    Ok(presets) if presets.is_empty() => {
+                        ui.label("No presets saved yet");
+                    }
+                    Ok(presets) => {
+                        for preset in presets.iter() {
+                            ui.horizontal(|ui| {
+                                if ui.selectable_label(false, &preset.name).clicked() {
+                                    chosen = Some(preset.to_owned());
+                                }
+                                if ui
+                                    .small_button("🗑")
+                                    .on_hover_text("Delete this preset")
+                                    .clicked()
+                                {
+                                    removed = Some(preset.name.to_owned());
+                                }
+                            });
+                        }
+                    }
+                    Err(err) => {
+                        ui.colored_label(Color32::RED, err);
+                    }
+                });
+            if let Some(preset) = chosen {
+                self.apply_preset(&preset);
+            }
+            if let Some(name) = removed {
+                self.delete_preset(&name);
+            }
+
+            ui.add(
+                TextEdit::singleline(&mut self.preset_name)
+                    .hint_text("Preset name")
+                    .desired_width(100.0),
+            );
+            let can_save = !self.preset_name.trim().is_empty();
+            let overwrites = self.presets.as_ref().map_or(false, |presets| {
+                presets.iter().any(|p| p.name == self.preset_name.trim())
+            });
+            let hover = match overwrites {
+                true => "Overwrite the preset with this name",
+                false => "Save the form as a preset",
+            };
+            if ui
+                .add_enabled(can_save, egui::Button::new("💾").small())
+                .on_hover_text(hover)
+                .clicked()
+            {
+                self.save_as_preset();
+            }
+        });
+    }
+
     // fn init_prompt_ui(&mut self, ui: &mut egui::Ui) {
     //     ui.vertical_centered(|ui| {
     //         let prompt_ui_rc = Rc::clone(&self.init_prompt_ui);
@@ -104,14 +261,44 @@ impl AgentInfoModal {
     // }
 
     fn recall_mode(&mut self, ui: &mut egui::Ui) {
-        let current_mode = &self.recall_mode;
-        ui.colored_label(
-            Color32::KHAKI,
-            format!("Current recall mode: {:?}", current_mode),
-        );
+        ui.indent("RecallOptions", |ui| {
+            recall_mode_options(ui, &mut self.recall, self.long_term.enabled);
+        });
+    }
+
+    fn long_term_options(&mut self, ui: &mut egui::Ui) {
+        ui.indent("LongTermOptions", |ui| {
+            ui.checkbox(&mut self.long_term.enabled, "Enabled")
+                .on_hover_text("Needs the long term memory database");
+            ui.add_enabled_ui(self.long_term.enabled, |ui| {
+                ui.horizontal(|ui| {
+                    let mut chosen = None;
+                    egui::ComboBox::from_id_source("long_term_thread_picker")
+                        .selected_text("Existing thread…")
+                        .show_ui(ui, |ui| {
+                            if self.long_term.known_threads.is_empty() {
+                                ui.label("No chats use long term memory yet");
+                            }
+                            for thread in self.long_term.known_threads.iter() {
+                                if ui.selectable_label(false, thread).clicked() {
+                                    chosen = Some(thread.to_owned());
+                                }
+                            }
+                        });
+                    if let Some(thread) = chosen {
+                        self.long_term.thread = thread;
+                    }
+                });
+                ui.add(
+                    TextEdit::singleline(&mut self.long_term.thread)
+                        .hint_text("Thread name, defaults to the chat's name"),
+                );
+            });
+        });
     }
 
     pub fn display_agent_form(&mut self, ui: &mut egui::Ui) {
+        self.preset_picker(ui);
         ui.add(egui::TextEdit::singleline(&mut self.chat_name).hint_text("New chat name"));
 
         if ui
@@ -123,6 +310,7 @@ impl AgentInfoModal {
         if self.open.system_prompt {
             self.open.recall_mode = false;
             self.open.caching_mechanism = false;
+            self.open.long_term_memory = false;
             InitPromptUi::overview_display(Rc::clone(&self.init_prompt_ui), ui);
         }
 
@@ -133,12 +321,13 @@ impl AgentInfoModal {
             {
                 self.open.recall_mode = !self.open.recall_mode;
             }
-            ui.colored_label(Color32::GOLD, format!("{:?}", &self.recall_mode));
+            ui.colored_label(Color32::GOLD, format!("{:?}", &self.recall));
         });
 
         if self.open.recall_mode {
             self.open.caching_mechanism = false;
             self.open.system_prompt = false;
+            self.open.long_term_memory = false;
             self.recall_mode(ui);
         }
 
@@ -155,7 +344,28 @@ impl AgentInfoModal {
         if self.open.caching_mechanism {
             self.open.recall_mode = false;
             self.open.system_prompt = false;
+            self.open.long_term_memory = false;
+            self.caching_mechanism_ui
+                .set_long_term_available(self.long_term.enabled);
             self.caching_mechanism_ui.overview_display(ui);
         }
+
+        ui.horizontal(|ui| {
+            if ui
+                .selectable_label(self.open.long_term_memory, "Long Term Memory")
+                .clicked()
+            {
+                self.open.long_term_memory = !self.open.long_term_memory;
+            }
+            let status = self.long_term_thread().unwrap_or("Off".to_string());
+            ui.colored_label(Color32::GOLD, status);
+        });
+
+        if self.open.long_term_memory {
+            self.open.recall_mode = false;
+            self.open.system_prompt = false;
+            self.open.caching_mechanism = false;
+            self.long_term_options(ui);
+        }
     }
 }
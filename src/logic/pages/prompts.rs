@@ -0,0 +1,475 @@
+use crate::logic::persistence::{
+    prompts::{
+        edit_prompts, load_prompts, read_prompt_pack, unique_prompt_name, update_prompt,
+        write_prompt_pack, PromptSaveError, PromptsWatcher, SavedPrompt,
+    },
+    SavedMessage,
+};
+use eframe::{
+    egui::{self, CentralPanel, RichText, SidePanel},
+    epaint::{Color32, FontId},
+};
+
+/// Browse and edit the prompts library that the New Chat modal loads init prompts from
+#[derive(Debug)]
+pub struct PromptsPage {
+    prompts: Vec<SavedPrompt>,
+    watcher: PromptsWatcher,
+    search: String,
+    selected: Option<String>,
+    rename: Option<String>,
+    new_tag: String,
+    /// The prompt as loaded and its edited copy
+    draft: Option<(SavedPrompt, SavedPrompt)>,
+    draft_conflict: bool,
+    error_message: Option<String>,
+}
+
+const ROLES: [&str; 3] = ["system", "user", "assistant"];
+
+impl PromptsPage {
+    pub fn init() -> Self {
+        let mut page = Self {
+            prompts: vec![],
+            watcher: PromptsWatcher::new(),
+            search: String::new(),
+            selected: None,
+            rename: None,
+            new_tag: String::new(),
+            draft: None,
+            draft_conflict: false,
+            error_message: None,
+        };
+        page.reload();
+        page
+    }
+
+    /// Keeps showing what was last read if the file can't be, edits are refused until
+    /// it's fixed
+    pub fn reload(&mut self) {
+        match load_prompts() {
+            Ok(prompts) => {
+                self.prompts = prompts;
+                self.error_message = None;
+            }
+            Err(err) => self.error_message = Some(err.to_string()),
+        }
+        self.watcher.mark_seen();
+        if let Some(name) = &self.selected {
+            if !self.prompts.iter().any(|p| &p.name == name) {
+                self.selected = None;
+            }
+        }
+    }
+
+    fn selected_prompt(&self) -> Option<&SavedPrompt> {
+        let name = self.selected.as_ref()?;
+        self.prompts.iter().find(|p| &p.name == name)
+    }
+
+    /// Matches the name, any tag, or any message content
+    fn matches_search(&self, prompt: &SavedPrompt) -> bool {
+        let search = self.search.trim().to_lowercase();
+        if search.is_empty() {
+            return true;
+        }
+        prompt.name.to_lowercase().contains(&search)
+            || prompt
+                .tags
+                .iter()
+                .any(|tag| tag.to_lowercase().contains(&search))
+            || prompt
+                .messages
+                .iter()
+                .any(|m| m.content.to_lowercase().contains(&search))
+    }
+
+    fn apply(&mut self, edit: impl FnOnce(&mut Vec<SavedPrompt>) -> Result<(), PromptSaveError>) {
+        match edit_prompts(edit) {
+            Ok(prompts) => {
+                self.prompts = prompts;
+                self.error_message = None;
+            }
+            Err(err) => self.error_message = Some(err.to_string()),
+        }
+        self.watcher.mark_seen();
+    }
+
+    fn new_prompt(&mut self) {
+        let name = unique_prompt_name("New prompt", &self.prompts);
+        let prompt = SavedPrompt {
+            name: name.to_owned(),
+            tags: vec![],
+            messages: vec![SavedMessage {
+                role: "system".to_string(),
+                content: String::new(),
+            }],
+        };
+        self.apply(|prompts| {
+            prompts.push(prompt);
+            Ok(())
+        });
+        self.selected = Some(name);
+        self.start_editing();
+    }
+
+    fn duplicate(&mut self, name: &str) {
+        let mut new_name = None;
+        self.apply(|prompts| {
+            let original = prompts
+                .iter()
+                .find(|p| p.name == name)
+                .cloned()
+                .ok_or_else(|| PromptSaveError::NotFound(name.to_string()))?;
+            let copy = SavedPrompt {
+                name: unique_prompt_name(&format!("{} copy", original.name), prompts),
+                ..original
+            };
+            new_name = Some(copy.name.to_owned());
+            prompts.push(copy);
+            Ok(())
+        });
+        if new_name.is_some() {
+            self.selected = new_name;
+        }
+    }
+
+    fn rename_prompt(&mut self, from: &str, to: String) {
+        let to = to.trim().to_string();
+        if to.is_empty() || to == from {
+            return;
+        }
+        self.apply(|prompts| {
+            if prompts.iter().any(|p| p.name == to) {
+                return Err(PromptSaveError::NameTaken(to.to_owned()));
+            }
+            match prompts.iter_mut().find(|p| p.name == from) {
+                Some(prompt) => prompt.name = to.to_owned(),
+                None => return Err(PromptSaveError::NotFound(from.to_string())),
+            }
+            Ok(())
+        });
+        if self.error_message.is_none() {
+            if let Some((original, edited)) = &mut self.draft {
+                if original.name == from {
+                    original.name = to.to_owned();
+                    edited.name = to.to_owned();
+                }
+            }
+            self.selected = Some(to);
+        }
+    }
+
+    fn delete(&mut self, name: &str) {
+        self.apply(|prompts| {
+            prompts.retain(|p| p.name != name);
+            Ok(())
+        });
+        self.selected = None;
+        self.draft = None;
+    }
+
+    fn set_tags(&mut self, name: &str, tags: Vec<String>) {
+        self.apply(|prompts| {
+            match prompts.iter_mut().find(|p| p.name == name) {
+                Some(prompt) => prompt.tags = tags.to_owned(),
+                None => return Err(PromptSaveError::NotFound(name.to_string())),
+            }
+            Ok(())
+        });
+        // Tags are saved straight away, an open draft shouldn't see that as a conflict
+        if let Some((original, edited)) = &mut self.draft {
+            if original.name == name {
+                original.tags = tags.to_owned();
+                edited.tags = tags;
+            }
+        }
+    }
+
+    fn start_editing(&mut self) {
+        if let Some(prompt) = self.selected_prompt() {
+            self.draft = Some((prompt.to_owned(), prompt.to_owned()));
+            self.draft_conflict = false;
+        }
+    }
+
+    fn save_draft(&mut self, force: bool) {
+        if let Some((original, edited)) = &self.draft {
+            match update_prompt(original, edited.to_owned(), force) {
+                Ok(_) => {
+                    self.draft = None;
+                    self.draft_conflict = false;
+                    self.error_message = None;
+                    self.reload();
+                }
+                Err(err) => {
+                    self.draft_conflict = matches!(err, PromptSaveError::Conflict(_));
+                    self.error_message = Some(err.to_string());
+                }
+            }
+        }
+    }
+
+    fn import_pack(&mut self) {
+        let path = match rfd::FileDialog::new()
+            .add_filter("prompt pack", &["yaml", "yml"])
+            .pick_file()
+        {
+            Some(path) => path,
+            None => return,
+        };
+        match read_prompt_pack(&path) {
+            Ok(imported) => self.apply(|prompts| {
+                for mut prompt in imported.into_iter() {
+                    prompt.name = unique_prompt_name(&prompt.name, prompts);
+                    prompts.push(prompt);
+                }
+                Ok(())
+            }),
+            Err(err) => self.error_message = Some(format!("Import failed: {}", err)),
+        }
+    }
+
+    /// Exports whatever the search currently shows
+    fn export_pack(&mut self) {
+        let visible: Vec<SavedPrompt> = self
+            .prompts
+            .iter()
+            .filter(|p| self.matches_search(p))
+            .cloned()
+            .collect();
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name("prompts.yaml")
+            .add_filter("prompt pack", &["yaml", "yml"])
+            .save_file()
+        {
+            if let Err(err) = write_prompt_pack(&path, visible) {
+                self.error_message = Some(format!("Export failed: {}", err));
+            }
+        }
+    }
+
+    fn display_list(&mut self, ui: &mut egui::Ui) {
+        ui.add(egui::TextEdit::singleline(&mut self.search).hint_text("🔍 name, tag or text"));
+        ui.horizontal(|ui| {
+            if ui.button("➕").on_hover_text("New prompt").clicked() {
+                self.new_prompt();
+            }
+            if ui.button("📥").on_hover_text("Import YAML pack").clicked() {
+                self.import_pack();
+            }
+            if ui
+                .button("📤")
+                .on_hover_text("Export shown prompts")
+                .clicked()
+            {
+                self.export_pack();
+            }
+        });
+        ui.separator();
+
+        let visible: Vec<(String, Vec<String>)> = self
+            .prompts
+            .iter()
+            .filter(|p| self.matches_search(p))
+            .map(|p| (p.name.to_owned(), p.tags.to_owned()))
+            .collect();
+        egui::ScrollArea::vertical()
+            .id_source("prompts_list")
+            .show(ui, |ui| {
+                for (name, tags) in visible.into_iter() {
+                    let is_selected = self.selected.as_ref() == Some(&name);
+                    let label = ui.selectable_label(is_selected, &name);
+                    if !tags.is_empty() {
+                        ui.colored_label(Color32::GRAY, format!("  #{}", tags.join(" #")));
+                    }
+                    if label.clicked() && !is_selected {
+                        self.selected = Some(name);
+                        self.rename = None;
+                        self.draft = None;
+                    }
+                }
+            });
+    }
+
+    fn display_tags(&mut self, prompt: &SavedPrompt, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Tags:");
+            let mut tags = prompt.tags.to_owned();
+            let mut changed = false;
+            for tag in prompt.tags.iter() {
+                if ui
+                    .small_button(format!("#{} ✖", tag))
+                    .on_hover_text("Remove tag")
+                    .clicked()
+                {
+                    tags.retain(|t| t != tag);
+                    changed = true;
+                }
+            }
+            let tag_input = ui.add(
+                egui::TextEdit::singleline(&mut self.new_tag)
+                    .hint_text("add tag")
+                    .desired_width(80.0),
+            );
+            let new_tag = self.new_tag.trim().to_string();
+            if tag_input.lost_focus()
+                && ui.input(|i| i.key_pressed(egui::Key::Enter))
+                && !new_tag.is_empty()
+                && !tags.contains(&new_tag)
+            {
+                tags.push(new_tag);
+                self.new_tag.clear();
+                changed = true;
+            }
+            if changed {
+                self.set_tags(&prompt.name, tags);
+            }
+        });
+    }
+
+    fn display_preview(prompt: &SavedPrompt, ui: &mut egui::Ui) {
+        for message in prompt.messages.iter() {
+            ui.colored_label(Color32::GOLD, &message.role);
+            let mut content = message.content.to_owned();
+            ui.add(
+                egui::TextEdit::multiline(&mut content)
+                    .desired_width(f32::INFINITY)
+                    .frame(false)
+                    .interactive(false),
+            );
+            ui.add_space(4.0);
+        }
+    }
+
+    fn display_editor(edited: &mut SavedPrompt, ui: &mut egui::Ui) {
+        let mut remove = None;
+        for (i, message) in edited.messages.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_id_source(format!("prompt_role_{}", i))
+                    .selected_text(&message.role)
+                    .show_ui(ui, |ui| {
+                        for role in ROLES {
+                            ui.selectable_value(&mut message.role, role.to_string(), role);
+                        }
+                    });
+                if ui
+                    .small_button("🗑")
+                    .on_hover_text("Remove message")
+                    .clicked()
+                {
+                    remove = Some(i);
+                }
+            });
+            ui.add(
+                egui::TextEdit::multiline(&mut message.content)
+                    .desired_width(f32::INFINITY)
+                    .desired_rows(3),
+            );
+            ui.add_space(4.0);
+        }
+        if let Some(i) = remove {
+            edited.messages.remove(i);
+        }
+        if ui.small_button("+ message").clicked() {
+            edited.messages.push(SavedMessage {
+                role: "user".to_string(),
+                content: String::new(),
+            });
+        }
+    }
+
+    fn display_selected(&mut self, ui: &mut egui::Ui) {
+        let prompt = match self.selected_prompt() {
+            Some(prompt) => prompt.to_owned(),
+            None => {
+                ui.label("Select a prompt");
+                return;
+            }
+        };
+
+        ui.horizontal(|ui| {
+            match &mut self.rename {
+                Some(new_name) => {
+                    let input = ui.text_edit_singleline(new_name);
+                    if ui.small_button("✔").clicked()
+                        || (input.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)))
+                    {
+                        let new_name = self.rename.take().unwrap_or_default();
+                        self.rename_prompt(&prompt.name, new_name);
+                    }
+                }
+                None => {
+                    ui.colored_label(
+                        Color32::LIGHT_BLUE,
+                        RichText::new(&prompt.name)
+                            .font(FontId::proportional(18.0))
+                            .strong(),
+                    );
+                    if ui.small_button("✏").on_hover_text("Rename").clicked() {
+                        self.rename = Some(prompt.name.to_owned());
+                    }
+                }
+            }
+            ui.add_space(10.0);
+            if ui.small_button("Duplicate").clicked() {
+                self.duplicate(&prompt.name);
+            }
+            if ui.small_button("❌").on_hover_text("Delete").clicked() {
+                self.delete(&prompt.name);
+            }
+        });
+        self.display_tags(&prompt, ui);
+        ui.separator();
+
+        ui.horizontal(|ui| match self.draft.is_some() {
+            true => {
+                if ui.button("💾").clicked() {
+                    self.save_draft(false);
+                }
+                if self.draft_conflict && ui.button("Overwrite anyway").clicked() {
+                    self.save_draft(true);
+                }
+                if ui.button("Discard").clicked() {
+                    self.draft = None;
+                    self.draft_conflict = false;
+                    self.error_message = None;
+                }
+            }
+            false => {
+                if ui.button("Edit").clicked() {
+                    self.start_editing();
+                }
+            }
+        });
+
+        egui::ScrollArea::vertical()
+            .id_source("prompt_preview")
+            .auto_shrink([false; 2])
+            .show(ui, |ui| match &mut self.draft {
+                Some((_, edited)) => Self::display_editor(edited, ui),
+                None => Self::display_preview(&prompt, ui),
+            });
+    }
+
+    pub fn display(&mut self, ui: &mut egui::Ui) {
+        // Edits in progress keep their own copy, so the list can be swapped out under them
+        if self.watcher.changed() {
+            tracing::info!("Prompts file changed on disk, reloading");
+            self.reload();
+        }
+
+        SidePanel::new(egui::panel::Side::Left, "PromptsPanel")
+            .resizable(true)
+            .show(ui.ctx(), |ui| {
+                self.display_list(ui);
+            });
+
+        CentralPanel::default().show(ui.ctx(), |ui| {
+            if let Some(err) = &self.error_message {
+                ui.colored_label(Color32::RED, err);
+            }
+            self.display_selected(ui);
+        });
+    }
+}
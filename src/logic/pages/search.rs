@@ -0,0 +1,225 @@
+use super::chat::ChatPage;
+use crate::logic::persistence::{role_to_str, workspaces::current_workspace};
+use chrono::{DateTime, Local, Utc};
+use eframe::{
+    egui::{self, text::LayoutJob, Align2, TextFormat},
+    epaint::{Color32, FontId},
+};
+
+const SNIPPET_CONTEXT: usize = 60;
+const MAX_RESULTS: usize = 200;
+
+/// Top bar search over every message of every chat in the current workspace. Other
+/// workspaces' chats aren't loaded, switch to one to search it
+#[derive(Debug, Default)]
+pub struct GlobalSearch {
+    query: String,
+    results: Vec<SearchHit>,
+    /// Workspace, query and message count the results were computed for
+    searched: Option<(String, String, usize)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub chat_name: String,
+    pub message_idx: usize,
+    pub role: String,
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Snippet text plus the byte range of the match inside it
+    pub snippet: String,
+    pub highlight: (usize, usize),
+}
+
+/// Byte range of the first case insensitive match of `needle` in `haystack`
+fn find_case_insensitive(haystack: &str, needle: &str) -> Option<(usize, usize)> {
+    let needle: Vec<char> = needle.chars().flat_map(char::to_lowercase).collect();
+    if needle.is_empty() {
+        return None;
+    }
+    for (start, _) in haystack.char_indices() {
+        let mut matched = 0;
+        for (offset, c) in haystack[start..].char_indices() {
+            let lower: Vec<char> = c.to_lowercase().collect();
+            if needle.get(matched..matched + lower.len()) != Some(&lower[..]) {
+                break;
+            }
+            matched += lower.len();
+            if matched == needle.len() {
+                return Some((start, start + offset + c.len_utf8()));
+            }
+        }
+    }
+    None
+}
+
+/// Cuts `content` down to the match with some context either side, on one line
+fn snippet(content: &str, (start, end): (usize, usize)) -> (String, (usize, usize)) {
+    let mut from = start.saturating_sub(SNIPPET_CONTEXT);
+    while !content.is_char_boundary(from) {
+        from -= 1;
+    }
+    let mut to = (end + SNIPPET_CONTEXT).min(content.len());
+    while !content.is_char_boundary(to) {
+        to += 1;
+    }
+    let prefix = match from > 0 {
+        true => "…",
+        false => "",
+    };
+    let suffix = match to < content.len() {
+        true => "…",
+        false => "",
+    };
+    let text = format!("{}{}{}", prefix, &content[from..to], suffix).replace('\n', " ");
+    let offset = prefix.len();
+    (text, (start - from + offset, end - from + offset))
+}
+
+impl GlobalSearch {
+    fn search(&mut self, chat_page: &ChatPage) {
+        let message_count: usize = chat_page
+            .all_chat_names()
+            .iter()
+            .filter_map(|name| chat_page.chat(name))
+            .map(|chat| chat.conversation().count())
+            .sum();
+        let key = (
+            current_workspace(),
+            self.query.trim().to_string(),
+            message_count,
+        );
+        if self.searched.as_ref() == Some(&key) {
+            return;
+        }
+        self.results.clear();
+        for name in chat_page.all_chat_names().iter() {
+            let chat = match chat_page.chat(name) {
+                Some(chat) => chat,
+                None => continue,
+            };
+            for (idx, message) in chat.conversation() {
+                if self.results.len() >= MAX_RESULTS {
+                    break;
+                }
+                let content = message.content().unwrap_or_default();
+                if let Some(range) = find_case_insensitive(&content, &key.1) {
+                    let (snippet, highlight) = snippet(&content, range);
+                    self.results.push(SearchHit {
+                        chat_name: name.to_owned(),
+                        message_idx: idx,
+                        role: role_to_str(&message.role()).to_string(),
+                        timestamp: chat.message_meta().get(idx).and_then(|meta| meta.timestamp),
+                        snippet,
+                        highlight,
+                    });
+                }
+            }
+        }
+        self.searched = Some(key);
+    }
+
+    /// The search field, Ctrl+Shift+F focuses it and Escape clears it
+    pub fn display_field(&mut self, ui: &mut egui::Ui) {
+        let field = ui
+            .add(
+                egui::TextEdit::singleline(&mut self.query)
+                    .hint_text("🔍 Search chats")
+                    .desired_width(180.0),
+            )
+            .on_hover_text(format!(
+                "Searches the chats in the {} workspace",
+                current_workspace()
+            ));
+        let focus_shortcut =
+            ui.input(|i| i.modifiers.command && i.modifiers.shift && i.key_pressed(egui::Key::F));
+        if focus_shortcut {
+            field.request_focus();
+        }
+        if field.has_focus() && ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.query.clear();
+        }
+    }
+
+    fn highlighted_snippet(hit: &SearchHit) -> LayoutJob {
+        let mut job = LayoutJob::default();
+        let normal = TextFormat {
+            font_id: FontId::proportional(13.0),
+            color: Color32::LIGHT_GRAY,
+            ..Default::default()
+        };
+        let highlighted = TextFormat {
+            color: Color32::BLACK,
+            background: Color32::GOLD,
+            ..normal.clone()
+        };
+        let (start, end) = hit.highlight;
+        job.append(&hit.snippet[..start], 0.0, normal.clone());
+        job.append(&hit.snippet[start..end], 0.0, highlighted);
+        job.append(&hit.snippet[end..], 0.0, normal);
+        job
+    }
+
+    /// Shows results under the top bar while there's a query. Returns true when one was
+    /// clicked, after jumping the chat page to it
+    pub fn display_results(&mut self, chat_page: &mut ChatPage, ctx: &egui::Context) -> bool {
+        if self.query.trim().is_empty() {
+            self.results.clear();
+            self.searched = None;
+            return false;
+        }
+        self.search(chat_page);
+
+        let mut chosen = None;
+        egui::Window::new(format!("Search results in {}", current_workspace()))
+            .id(egui::Id::new("search_results_window"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(Align2::RIGHT_TOP, [-10.0, 40.0])
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                match self.results.len() {
+                    0 => ui.label("No matches"),
+                    MAX_RESULTS => ui.label(format!("First {} matches", MAX_RESULTS)),
+                    n => ui.label(format!("{} matches", n)),
+                };
+                egui::ScrollArea::vertical()
+                    .id_source("search_results")
+                    .max_height(400.0)
+                    .show(ui, |ui| {
+                        for hit in self.results.iter() {
+                            ui.separator();
+                            ui.horizontal(|ui| {
+                                ui.colored_label(Color32::LIGHT_BLUE, &hit.chat_name);
+                                ui.colored_label(Color32::GOLD, &hit.role);
+                                if let Some(timestamp) = hit.timestamp {
+                                    ui.colored_label(
+                                        Color32::GRAY,
+                                        timestamp
+                                            .with_timezone(&Local)
+                                            .format("%Y-%m-%d %H:%M")
+                                            .to_string(),
+                                    );
+                                }
+                            });
+                            let snippet = ui.add(
+                                egui::Label::new(Self::highlighted_snippet(hit))
+                                    .wrap(true)
+                                    .sense(egui::Sense::click()),
+                            );
+                            if snippet.clicked() {
+                                chosen = Some(hit.to_owned());
+                            }
+                            snippet.on_hover_cursor(egui::CursorIcon::PointingHand);
+                        }
+                    });
+            });
+
+        match chosen {
+            Some(hit) => {
+                chat_page.jump_to_message(&hit.chat_name, hit.message_idx);
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -1,8 +1,30 @@
 use eframe::epaint::Color32;
+use std::time::Duration;
 
-use super::{super::backend::AppBackend, egui, PageDisplay};
+use super::{
+    super::{
+        backend::{
+            database::{DatabaseSettings, DatabaseStatus},
+            retry::RetryPolicy,
+            semantic::{EmbeddingProviderKind, SemanticRecallSettings},
+            settings::CompletionSettings,
+            watchdog::StallWatchdog,
+        },
+        comms::{BackendCommand, FrontendComms},
+        persistence::{database::load_database_settings, settings::load_completion_settings},
+    },
+    egui,
+};
 
-pub struct SettingsPage {}
+#[derive(Debug)]
+pub struct SettingsPage {
+    completion_settings: CompletionSettings,
+    database: DatabaseSettings,
+    /// `Unknown` while a test is running
+    database_test: Option<DatabaseStatus>,
+    /// The saved database settings aren't the ones in use
+    restart_required: bool,
+}
 
 #[derive(Debug)]
 pub struct GlobalSettings {
@@ -17,7 +39,183 @@ struct ChatSettings {
 }
 
 impl SettingsPage {
-    fn display() {
-        unimplemented!()
+    pub fn init() -> Self {
+        Self {
+            completion_settings: load_completion_settings(),
+            database: load_database_settings(),
+            database_test: None,
+            restart_required: false,
+        }
+    }
+
+    pub fn database_tested(&mut self, status: DatabaseStatus) {
+        self.database_test = Some(status);
+    }
+
+    pub fn set_restart_required(&mut self, required: bool) {
+        self.restart_required = required;
+    }
+
+    /// Picks up the settings of a workspace that was just switched to
+    pub fn reload(&mut self) {
+        self.completion_settings = load_completion_settings();
     }
+
+    pub fn display(&mut self, frontend: &FrontendComms, ui: &mut egui::Ui) {
+        ui.heading("Retries");
+        ui.label("Used by every chat that doesn't set its own policy");
+        retry_policy_form(ui, &mut self.completion_settings.retry);
+
+        ui.add_space(10.0);
+        ui.heading("Stall watchdog");
+        ui.label("Aborts a response that stops producing tokens");
+        watchdog_form(ui, &mut self.completion_settings.watchdog);
+
+        ui.add_space(10.0);
+        ui.heading("Semantic recall");
+        ui.label("Offers related snippets from other chats and long term memory while typing");
+        semantic_recall_form(ui, &mut self.completion_settings.semantic_recall);
+
+        ui.add_space(10.0);
+        if ui.button("💾").clicked() {
+            frontend
+                .sender
+                .try_send(BackendCommand::SetCompletionSettings {
+                    settings: self.completion_settings.clone(),
+                })
+                .unwrap();
+        }
+
+        ui.add_space(10.0);
+        ui.heading("Long term memory database");
+        ui.label("Shared by every workspace, stored outside the project directory");
+        database_form(ui, &mut self.database);
+        ui.horizontal(|ui| {
+            let testing = self.database_test == Some(DatabaseStatus::Unknown);
+            if ui
+                .add_enabled(!testing, egui::Button::new("Test connection"))
+                .clicked()
+            {
+                self.database_test = Some(DatabaseStatus::Unknown);
+                frontend
+                    .sender
+                    .try_send(BackendCommand::TestDatabaseConnection {
+                        settings: self.database.clone(),
+                    })
+                    .unwrap();
+            }
+            if ui
+                .button("💾")
+                .on_hover_text("Used from the next time the app starts")
+                .clicked()
+            {
+                frontend
+                    .sender
+                    .try_send(BackendCommand::SetDatabaseSettings {
+                        settings: self.database.clone(),
+                    })
+                    .unwrap();
+            }
+            match &self.database_test {
+                Some(DatabaseStatus::Unknown) => {
+                    ui.spinner();
+                }
+                Some(DatabaseStatus::Connected) => {
+                    ui.colored_label(Color32::GREEN, "Connected");
+                }
+                Some(DatabaseStatus::Disconnected(err)) => {
+                    ui.colored_label(Color32::RED, err);
+                }
+                None => {}
+            }
+        });
+        if self.restart_required {
+            ui.colored_label(
+                Color32::GOLD,
+                "Restart required, long term memory uses the database the app started with \
+                 until then",
+            );
+        }
+    }
+}
+
+pub fn semantic_recall_form(ui: &mut egui::Ui, recall: &mut SemanticRecallSettings) {
+    ui.checkbox(&mut recall.enabled, "Enabled");
+    ui.add_enabled_ui(recall.enabled, |ui| {
+        egui::ComboBox::from_label("Embeddings")
+            .selected_text(recall.provider.to_string())
+            .show_ui(ui, |ui| {
+                for provider in EmbeddingProviderKind::all() {
+                    ui.selectable_value(&mut recall.provider, provider, provider.to_string());
+                }
+            });
+        ui.add(egui::Slider::new(&mut recall.max_results, 1..=10).text("Max snippets"));
+        ui.add(egui::Slider::new(&mut recall.min_score, 0.0..=1.0).text("Min similarity"));
+    });
+}
+
+pub fn database_form(ui: &mut egui::Ui, database: &mut DatabaseSettings) {
+    egui::Grid::new("database_form")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Host");
+            ui.text_edit_singleline(&mut database.host);
+            ui.end_row();
+
+            ui.label("Port");
+            ui.add(egui::DragValue::new(&mut database.port));
+            ui.end_row();
+
+            ui.label("User");
+            ui.text_edit_singleline(&mut database.user);
+            ui.end_row();
+
+            ui.label("Password");
+            ui.add(egui::TextEdit::singleline(&mut database.password).password(true));
+            ui.end_row();
+
+            ui.label("Database");
+            ui.text_edit_singleline(&mut database.name);
+            ui.end_row();
+        });
+}
+
+pub fn retry_policy_form(ui: &mut egui::Ui, policy: &mut RetryPolicy) {
+    ui.add(egui::Slider::new(&mut policy.max_attempts, 1..=10).text("Max attempts"));
+
+    let mut initial_backoff = policy.initial_backoff.as_secs_f32();
+    ui.add(egui::Slider::new(&mut initial_backoff, 0.5..=30.0).text("Initial backoff (s)"));
+    policy.initial_backoff = Duration::from_secs_f32(initial_backoff);
+
+    ui.add(egui::Slider::new(&mut policy.backoff_multiplier, 1.0..=4.0).text("Backoff multiplier"));
+
+    let mut max_backoff = policy.max_backoff.as_secs_f32();
+    ui.add(egui::Slider::new(&mut max_backoff, 1.0..=120.0).text("Max backoff (s)"));
+    policy.max_backoff = Duration::from_secs_f32(max_backoff);
+
+    ui.label("Retry on");
+    ui.horizontal(|ui| {
+        ui.checkbox(&mut policy.retry_on.rate_limit, "Rate limits");
+        ui.checkbox(&mut policy.retry_on.timeout, "Timeouts");
+        ui.checkbox(&mut policy.retry_on.server_error, "5xx");
+    });
+}
+
+pub fn watchdog_form(ui: &mut egui::Ui, watchdog: &mut StallWatchdog) {
+    ui.checkbox(&mut watchdog.enabled, "Enabled");
+    ui.add_enabled_ui(watchdog.enabled, |ui| {
+        let mut first_token_timeout = watchdog.first_token_timeout.as_secs_f32();
+        ui.add(
+            egui::Slider::new(&mut first_token_timeout, 5.0..=300.0)
+                .text("First token timeout (s)"),
+        );
+        watchdog.first_token_timeout = Duration::from_secs_f32(first_token_timeout);
+
+        let mut inter_token_timeout = watchdog.inter_token_timeout.as_secs_f32();
+        ui.add(
+            egui::Slider::new(&mut inter_token_timeout, 2.0..=120.0)
+                .text("Inter-token timeout (s)"),
+        );
+        watchdog.inter_token_timeout = Duration::from_secs_f32(inter_token_timeout);
+    });
 }
@@ -0,0 +1,392 @@
+use super::{
+    data_dir, dir_name,
+    journal::{read_entries, Journal},
+    slug, SavedMessage,
+};
+use crate::logic::backend::{
+    config::{AgentConfig, CachingConfig, RecallConfig},
+    retry::RetryPolicy,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::SystemTime};
+
+const DISPLAY_FILE: &str = "display.json";
+const AGENT_FILE: &str = "agent.json";
+const DISPLAY_JOURNAL_FILE: &str = "display.journal.jsonl";
+const AGENT_JOURNAL_FILE: &str = "agent.journal.jsonl";
+
+/// What the frontend shows for a chat, written by the `ChatPage`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SavedDisplay {
+    pub name: String,
+    pub messages: Vec<SavedMessage>,
+    /// One entry per message
+    #[serde(default)]
+    pub meta: Vec<MessageMeta>,
+}
+
+/// Bookkeeping the frontend keeps alongside each displayed message
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct MessageMeta {
+    /// `None` when it isn't known, for messages saved or imported without one
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Estimated from the text, see `estimate_tokens`
+    #[serde(default)]
+    pub token_count: Option<usize>,
+    #[serde(default)]
+    pub incomplete: bool,
+    /// Marks a divider standing in for this many messages the agent dropped from memory.
+    /// The message holds their summary, if there was one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compacted: Option<usize>,
+}
+
+impl MessageMeta {
+    pub fn now() -> Self {
+        Self {
+            timestamp: Some(Utc::now()),
+            token_count: None,
+            incomplete: false,
+            compacted: None,
+        }
+    }
+}
+
+/// What the agent actually remembers, written by the chat's backend thread
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedAgent {
+    pub name: String,
+    pub config: AgentConfig,
+    pub memory: Vec<SavedMessage>,
+    #[serde(default)]
+    pub retry_override: Option<RetryPolicy>,
+    /// Keys of the messages in `memory` that were pushed rather than prompted
+    #[serde(default)]
+    pub pushed: Vec<u64>,
+}
+
+/// Everything that changes what a chat displays, in the order it happened
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DisplayEntry {
+    Message {
+        message: SavedMessage,
+        meta: MessageMeta,
+    },
+    /// Throws away whatever was streamed before, e.g. when a completion is retried
+    StreamStarted,
+    StreamToken {
+        token: String,
+    },
+    /// The stream failed without producing a message
+    StreamEnded,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AgentEntry {
+    Message { message: SavedMessage },
+    Pushed { key: u64, message: SavedMessage },
+    RetryPolicy { policy: Option<RetryPolicy> },
+    Caching { caching: CachingConfig },
+    Recall { recall: RecallConfig },
+    Pinned { message: SavedMessage, pinned: bool },
+}
+
+impl AgentEntry {
+    /// An entry for each part of the config that differs between the two
+    pub fn config_changes(before: &AgentConfig, after: &AgentConfig) -> Vec<Self> {
+        let mut entries = vec![];
+        if before.caching != after.caching {
+            entries.push(Self::Caching {
+                caching: after.caching.clone(),
+            });
+        }
+        if before.recall != after.recall {
+            entries.push(Self::Recall {
+                recall: after.recall.clone(),
+            });
+        }
+        for message in before.pinned.iter().filter(|pin| !after.is_pinned(pin)) {
+            entries.push(Self::Pinned {
+                message: message.to_owned(),
+                pinned: false,
+            });
+        }
+        for message in after.pinned.iter().filter(|pin| !before.is_pinned(pin)) {
+            entries.push(Self::Pinned {
+                message: message.to_owned(),
+                pinned: true,
+            });
+        }
+        entries
+    }
+}
+
+/// A chat's display rebuilt from its last snapshot and journal
+#[derive(Debug, Clone)]
+pub struct RecoveredDisplay {
+    pub display: SavedDisplay,
+    /// A response that was still streaming when the app went down. Unless it's empty,
+    /// nothing having arrived yet, it's already in `display` marked incomplete, but the
+    /// agent never got it
+    pub interrupted: Option<SavedMessage>,
+}
+
+pub fn chats_dir() -> PathBuf {
+    data_dir().join("chats")
+}
+
+/// Chats saved before names were percent-encoded are moved over the first time they're
+/// touched, as long as the directory is really theirs and not another name's that shared
+/// the old slug
+fn chat_path(name: &str) -> PathBuf {
+    let dir = chats_dir().join(dir_name(name));
+    let legacy = chats_dir().join(slug(name));
+    if !dir.exists() && legacy != dir && saved_name(&legacy).as_deref() == Some(name) {
+        if let Err(err) = std::fs::rename(&legacy, &dir) {
+            tracing::warn!(
+                "Couldn't move {} chat to {}: {:?}",
+                name,
+                dir.display(),
+                err
+            );
+            return legacy;
+        }
+    }
+    dir
+}
+
+/// The name a chat directory was saved under
+fn saved_name(dir: &std::path::Path) -> Option<String> {
+    #[derive(Deserialize)]
+    struct Named {
+        name: String,
+    }
+    [AGENT_FILE, DISPLAY_FILE].iter().find_map(|file| {
+        let contents = std::fs::read_to_string(dir.join(file)).ok()?;
+        serde_json::from_str::<Named>(&contents)
+            .ok()
+            .map(|named| named.name)
+    })
+}
+
+fn chat_dir(name: &str) -> anyhow::Result<PathBuf> {
+    let dir = chat_path(name);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn write_json<T: Serialize>(path: PathBuf, value: &T) -> anyhow::Result<()> {
+    // Write to a temp file first so a crash mid-write can't leave half a file behind
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_string_pretty(value)?)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+pub fn save_display(display: &SavedDisplay) -> anyhow::Result<()> {
+    write_json(chat_dir(&display.name)?.join(DISPLAY_FILE), display)
+}
+
+pub fn display_journal(name: &str) -> Journal {
+    Journal::open(chat_path(name).join(DISPLAY_JOURNAL_FILE))
+}
+
+pub fn agent_journal(name: &str) -> Journal {
+    Journal::open(chat_path(name).join(AGENT_JOURNAL_FILE))
+}
+
+/// Displays saved before `meta` existed marked incomplete messages by index
+#[derive(Deserialize)]
+struct LegacyDisplay {
+    #[serde(default)]
+    incomplete_messages: Vec<usize>,
+}
+
+fn load_display_snapshot(name: &str) -> Option<SavedDisplay> {
+    let path = chat_path(name).join(DISPLAY_FILE);
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut display: SavedDisplay = match serde_json::from_str(&contents) {
+        Ok(display) => display,
+        Err(err) => {
+            tracing::warn!("Couldn't parse saved display for {}: {:?}", name, err);
+            return None;
+        }
+    };
+    if let Ok(legacy) = serde_json::from_str::<LegacyDisplay>(&contents) {
+        display
+            .meta
+            .resize_with(display.messages.len(), MessageMeta::default);
+        for idx in legacy.incomplete_messages.into_iter() {
+            if let Some(meta) = display.meta.get_mut(idx) {
+                meta.incomplete = true;
+            }
+        }
+    }
+    Some(display)
+}
+
+/// The last snapshot with the journal replayed on top
+pub fn load_display(name: &str) -> Option<RecoveredDisplay> {
+    let journal_path = chat_path(name).join(DISPLAY_JOURNAL_FILE);
+    let entries: Vec<DisplayEntry> = read_entries(&journal_path);
+    let mut display = match load_display_snapshot(name) {
+        Some(display) => display,
+        None if entries.is_empty() => return None,
+        None => SavedDisplay {
+            name: name.to_string(),
+            ..Default::default()
+        },
+    };
+    display
+        .meta
+        .resize_with(display.messages.len(), MessageMeta::default);
+
+    let mut partial: Option<String> = None;
+    for entry in entries.into_iter() {
+        match entry {
+            DisplayEntry::Message { message, meta } => {
+                if message.role == "assistant" {
+                    partial = None;
+                }
+                display.messages.push(message);
+                display.meta.push(meta);
+            }
+            DisplayEntry::StreamStarted => partial = Some(String::new()),
+            DisplayEntry::StreamToken { token } => {
+                partial.get_or_insert_with(String::new).push_str(&token);
+            }
+            DisplayEntry::StreamEnded => partial = None,
+        }
+    }
+
+    let interrupted = partial.map(|content| {
+        let message = SavedMessage {
+            role: "assistant".to_string(),
+            content,
+        };
+        if !message.content.is_empty() {
+            display.messages.push(message.to_owned());
+            display.meta.push(MessageMeta {
+                incomplete: true,
+                ..MessageMeta::now()
+            });
+        }
+        message
+    });
+    if interrupted.is_some() {
+        tracing::warn!("Recovered an interrupted response for {}", name);
+    }
+    Some(RecoveredDisplay {
+        display,
+        interrupted,
+    })
+}
+
+/// Every chat directory with a display and when that display last changed, without
+/// reading any of them
+pub fn display_versions() -> Vec<(PathBuf, SystemTime)> {
+    let entries = match std::fs::read_dir(chats_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let dir = entry.path();
+            let changed = [DISPLAY_FILE, DISPLAY_JOURNAL_FILE]
+                .iter()
+                .filter_map(|file| std::fs::metadata(dir.join(file)).ok()?.modified().ok())
+                .max()?;
+            Some((dir, changed))
+        })
+        .collect()
+}
+
+/// `load_display` for a directory from `display_versions`
+pub fn load_display_at(dir: &std::path::Path) -> Option<RecoveredDisplay> {
+    load_display(&saved_name(dir)?)
+}
+
+pub fn save_agent(agent: &SavedAgent) -> anyhow::Result<()> {
+    write_json(chat_dir(&agent.name)?.join(AGENT_FILE), agent)
+}
+
+/// Replays the agent journal on top of a snapshot
+fn replay_agent_journal(dir: &std::path::Path, agent: &mut SavedAgent) {
+    let entries: Vec<AgentEntry> = read_entries(&dir.join(AGENT_JOURNAL_FILE));
+    for entry in entries.into_iter() {
+        match entry {
+            AgentEntry::Message { message } => agent.memory.push(message),
+            AgentEntry::Pushed { key, message } => {
+                agent.pushed.push(key);
+                agent.memory.push(message);
+            }
+            AgentEntry::RetryPolicy { policy } => agent.retry_override = policy,
+            AgentEntry::Caching { caching } => agent.config.caching = caching,
+            AgentEntry::Recall { recall } => agent.config.recall = recall,
+            AgentEntry::Pinned { message, pinned } => agent.config.set_pinned(message, pinned),
+        }
+    }
+}
+
+pub fn load_agent(name: &str) -> Option<SavedAgent> {
+    let dir = chat_path(name);
+    let contents = std::fs::read_to_string(dir.join(AGENT_FILE)).ok()?;
+    let mut agent = serde_json::from_str(&contents).ok()?;
+    replay_agent_journal(&dir, &mut agent);
+    Some(agent)
+}
+
+/// Every chat with a saved agent, in the order they were created
+pub fn load_agents() -> Vec<SavedAgent> {
+    let entries = match std::fs::read_dir(chats_dir()) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut agents: Vec<(std::time::SystemTime, SavedAgent)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path().join(AGENT_FILE);
+            let contents = std::fs::read_to_string(&path).ok()?;
+            let created = entry
+                .metadata()
+                .and_then(|m| m.created().or_else(|_| m.modified()))
+                .unwrap_or(std::time::UNIX_EPOCH);
+            match serde_json::from_str::<SavedAgent>(&contents) {
+                Ok(mut agent) => {
+                    replay_agent_journal(&entry.path(), &mut agent);
+                    Some((created, agent))
+                }
+                Err(err) => {
+                    tracing::warn!("Couldn't parse {}: {:?}", path.display(), err);
+                    None
+                }
+            }
+        })
+        .collect();
+    agents.sort_by_key(|(created, _)| *created);
+    agents.into_iter().map(|(_, agent)| agent).collect()
+}
+
+/// Every long term thread a saved chat uses, sorted and without duplicates
+pub fn long_term_threads() -> Vec<String> {
+    let mut threads: Vec<String> = load_agents()
+        .into_iter()
+        .filter_map(|agent| agent.config.long_term_thread)
+        .collect();
+    threads.sort();
+    threads.dedup();
+    threads
+}
+
+pub fn remove_chat(name: &str) -> anyhow::Result<()> {
+    let dir = chat_path(name);
+    if dir.exists() {
+        std::fs::remove_dir_all(dir)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use super::app_dir;
+use crate::logic::backend::database::DatabaseSettings;
+use std::{io::Write, path::PathBuf};
+
+const DATABASE_FILE: &str = "database.yaml";
+
+/// Shared by every workspace, they all talk to the same database
+pub fn database_file() -> PathBuf {
+    app_dir().join(DATABASE_FILE)
+}
+
+/// Falls back to the environment, so an existing `.env` keeps working until the settings
+/// are saved from the app
+pub fn load_database_settings() -> DatabaseSettings {
+    std::fs::read_to_string(database_file())
+        .ok()
+        .and_then(|contents| match serde_yaml::from_str(&contents) {
+            Ok(settings) => Some(settings),
+            Err(err) => {
+                tracing::warn!("Couldn't parse database settings file: {:?}", err);
+                None
+            }
+        })
+        .unwrap_or_else(DatabaseSettings::from_env)
+}
+
+/// The file holds the password, so on unix only the owner may ever read it. The temp
+/// file is created that way rather than narrowed afterwards, then renamed over the old one
+pub fn save_database_settings(settings: &DatabaseSettings) -> anyhow::Result<()> {
+    std::fs::create_dir_all(app_dir())?;
+    let path = database_file();
+    let tmp = path.with_extension("yaml.tmp");
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    // A leftover temp file keeps whatever mode it was created with
+    let _ = std::fs::remove_file(&tmp);
+    options
+        .open(&tmp)?
+        .write_all(serde_yaml::to_string(settings)?.as_bytes())?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
@@ -0,0 +1,236 @@
+use super::{chats::MessageMeta, role_to_str};
+use crate::logic::backend::config::AgentConfig;
+use chrono::{DateTime, Utc};
+use espionox::memory::MessageVector;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+    Html,
+}
+
+/// A chat as it's written out by "Export…", also what "Import chat" reads back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatTranscript {
+    pub name: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// So an import comes back with the same memory settings, older exports don't have it
+    #[serde(default)]
+    pub config: Option<AgentConfig>,
+    pub exported_at: DateTime<Utc>,
+    pub messages: Vec<TranscriptMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptMessage {
+    pub role: String,
+    pub content: String,
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub token_count: Option<usize>,
+    #[serde(default)]
+    pub incomplete: bool,
+}
+
+impl ExportFormat {
+    pub fn all() -> Vec<Self> {
+        vec![Self::Markdown, Self::Json, Self::Html]
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Html => "html",
+        }
+    }
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Markdown => write!(f, "Markdown"),
+            Self::Json => write!(f, "JSON"),
+            Self::Html => write!(f, "HTML"),
+        }
+    }
+}
+
+fn role_heading(role: &str) -> &'static str {
+    match role {
+        "user" => "User",
+        "assistant" => "Assistant",
+        _ => "System",
+    }
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Text between ``` fences becomes a code block, everything else paragraphs
+fn content_to_html(content: &str) -> String {
+    let mut html = String::new();
+    for (i, chunk) in content.split("```").enumerate() {
+        match i % 2 {
+            0 => {
+                for paragraph in chunk.split("\n\n").filter(|p| !p.trim().is_empty()) {
+                    html.push_str(&format!(
+                        "<p>{}</p>\n",
+                        escape_html(paragraph.trim()).replace('\n', "<br>")
+                    ));
+                }
+            }
+            _ => {
+                // The first line of a fence is its language tag
+                let (lang, code) = match chunk.split_once('\n') {
+                    Some((lang, code)) => (lang.trim(), code),
+                    None => ("", chunk),
+                };
+                html.push_str(&format!(
+                    "<pre><code class=\"language-{}\">{}</code></pre>\n",
+                    escape_html(lang),
+                    escape_html(code)
+                ));
+            }
+        }
+    }
+    html
+}
+
+const HTML_STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; max-width: 860px; margin: 2rem auto; padding: 0 1rem; background: #1e1e2e; color: #cdd6f4; }
+h1 { color: #89b4fa; }
+.meta { color: #7f849c; font-size: 0.85rem; }
+.message { border-radius: 8px; padding: 0.75rem 1rem; margin: 1rem 0; }
+.user { background: #313244; border-left: 4px solid #f38ba8; }
+.assistant { background: #262637; border-left: 4px solid #89b4fa; }
+.system { background: #262626; border-left: 4px solid #f9e2af; }
+.role { font-weight: bold; margin-bottom: 0.5rem; }
+.incomplete { color: #f9e2af; font-size: 0.85rem; }
+pre { background: #11111b; padding: 0.75rem; border-radius: 6px; overflow-x: auto; }
+code { font-family: "Fira Code", Menlo, Consolas, monospace; }
+"#;
+
+impl ChatTranscript {
+    pub fn new(
+        name: &str,
+        model: Option<String>,
+        config: Option<AgentConfig>,
+        messages: &MessageVector,
+        meta: &[MessageMeta],
+    ) -> Self {
+        // Memory dividers aren't part of the conversation
+        let messages = messages
+            .as_ref()
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| meta.get(*i).map_or(true, |m| m.compacted.is_none()))
+            .map(|(i, message)| {
+                let meta = meta.get(i);
+                TranscriptMessage {
+                    role: role_to_str(&message.role()).to_string(),
+                    content: message.content().unwrap_or_default(),
+                    timestamp: meta.and_then(|m| m.timestamp),
+                    token_count: meta.and_then(|m| m.token_count),
+                    incomplete: meta.map_or(false, |m| m.incomplete),
+                }
+            })
+            .collect();
+        Self {
+            name: name.to_string(),
+            model,
+            config,
+            exported_at: Utc::now(),
+            messages,
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# {}\n\n", self.name);
+        if let Some(model) = &self.model {
+            md.push_str(&format!("*Model: {}*  \n", model));
+        }
+        md.push_str(&format!(
+            "*Exported: {}*\n\n",
+            self.exported_at.format("%Y-%m-%d %H:%M UTC")
+        ));
+        for message in self.messages.iter() {
+            md.push_str(&format!("## {}\n\n", role_heading(&message.role)));
+            if let Some(timestamp) = message.timestamp {
+                md.push_str(&format!(
+                    "*{}*\n\n",
+                    timestamp.format("%Y-%m-%d %H:%M:%S UTC")
+                ));
+            }
+            md.push_str(message.content.trim());
+            md.push_str("\n\n");
+            if message.incomplete {
+                md.push_str("> ⚠ Response incomplete\n\n");
+            }
+        }
+        md
+    }
+
+    pub fn to_json(&self) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut body = format!("<h1>{}</h1>\n", escape_html(&self.name));
+        let mut meta_line = format!("Exported {}", self.exported_at.format("%Y-%m-%d %H:%M UTC"));
+        if let Some(model) = &self.model {
+            meta_line.push_str(&format!(" · {}", escape_html(model)));
+        }
+        body.push_str(&format!("<div class=\"meta\">{}</div>\n", meta_line));
+
+        for message in self.messages.iter() {
+            let role = match message.role.as_str() {
+                "user" | "assistant" => message.role.as_str(),
+                _ => "system",
+            };
+            body.push_str(&format!("<div class=\"message {}\">\n", role));
+            body.push_str(&format!(
+                "<div class=\"role\">{}</div>\n",
+                role_heading(&message.role)
+            ));
+            if let Some(timestamp) = message.timestamp {
+                let mut line = timestamp.format("%Y-%m-%d %H:%M:%S UTC").to_string();
+                if let Some(tokens) = message.token_count {
+                    line.push_str(&format!(" · {} tokens", tokens));
+                }
+                body.push_str(&format!("<div class=\"meta\">{}</div>\n", line));
+            }
+            body.push_str(&content_to_html(&message.content));
+            if message.incomplete {
+                body.push_str("<div class=\"incomplete\">⚠ Response incomplete</div>\n");
+            }
+            body.push_str("</div>\n");
+        }
+
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            escape_html(&self.name),
+            HTML_STYLE,
+            body
+        )
+    }
+
+    pub fn write(&self, format: ExportFormat, path: &Path) -> anyhow::Result<()> {
+        let contents = match format {
+            ExportFormat::Markdown => self.to_markdown(),
+            ExportFormat::Json => self.to_json()?,
+            ExportFormat::Html => self.to_html(),
+        };
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+}
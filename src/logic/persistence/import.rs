@@ -0,0 +1,164 @@
+use super::{
+    chats::MessageMeta,
+    export::{ChatTranscript, TranscriptMessage},
+    role_from_str, role_to_str, SavedMessage,
+};
+use crate::logic::backend::config::AgentConfig;
+use chrono::{TimeZone, Utc};
+use serde_json::Value;
+use std::path::Path;
+
+/// One conversation pulled out of an imported file
+#[derive(Debug, Clone)]
+pub struct ImportedChat {
+    pub name: String,
+    pub messages: Vec<SavedMessage>,
+    pub meta: Vec<MessageMeta>,
+    /// Only the app's own exports carry one
+    pub config: Option<AgentConfig>,
+}
+
+impl From<ChatTranscript> for ImportedChat {
+    fn from(value: ChatTranscript) -> Self {
+        let (messages, meta) = value
+            .messages
+            .into_iter()
+            .map(|message| {
+                let meta = MessageMeta {
+                    timestamp: message.timestamp,
+                    token_count: message.token_count,
+                    incomplete: message.incomplete,
+                    compacted: None,
+                };
+                (saved_message(&message.role, message.content), meta)
+            })
+            .unzip();
+        Self {
+            name: value.name,
+            messages,
+            meta,
+            config: value.config,
+        }
+    }
+}
+
+fn saved_message(role: &str, content: String) -> SavedMessage {
+    SavedMessage {
+        role: role_to_str(&role_from_str(role)).to_string(),
+        content,
+    }
+}
+
+/// Reads the app's own JSON export, a bare `[{role, content}]` array, or a ChatGPT
+/// `conversations.json`
+pub fn parse_conversations(path: &Path) -> anyhow::Result<Vec<ImportedChat>> {
+    let contents = std::fs::read_to_string(path)?;
+    let fallback_name = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or("Imported chat".to_string());
+    let value: Value = serde_json::from_str(&contents)?;
+
+    if let Ok(transcript) = serde_json::from_value::<ChatTranscript>(value.clone()) {
+        return Ok(vec![transcript.into()]);
+    }
+
+    let items = match &value {
+        Value::Array(items) => items,
+        _ => return Err(anyhow::anyhow!("Unrecognized chat format")),
+    };
+
+    if items.iter().all(|item| item.get("mapping").is_some()) && !items.is_empty() {
+        let chats: Vec<ImportedChat> = items
+            .iter()
+            .filter_map(parse_chatgpt_conversation)
+            .collect();
+        return match chats.is_empty() {
+            true => Err(anyhow::anyhow!("No conversations with messages found")),
+            false => Ok(chats),
+        };
+    }
+
+    let messages: Vec<TranscriptMessage> = serde_json::from_value(value)
+        .map_err(|err| anyhow::anyhow!("Unrecognized chat format: {}", err))?;
+    let transcript = ChatTranscript {
+        name: fallback_name,
+        model: None,
+        config: None,
+        exported_at: Utc::now(),
+        messages,
+    };
+    Ok(vec![transcript.into()])
+}
+
+/// ChatGPT stores each conversation as a tree, the visible thread is the path from
+/// `current_node` back up to the root
+fn parse_chatgpt_conversation(conversation: &Value) -> Option<ImportedChat> {
+    let mapping = conversation.get("mapping")?.as_object()?;
+    let name = conversation
+        .get("title")
+        .and_then(Value::as_str)
+        .unwrap_or("ChatGPT conversation")
+        .to_string();
+
+    let mut node_id = conversation
+        .get("current_node")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let mut thread = vec![];
+    while let Some(id) = node_id {
+        let node = mapping.get(&id)?;
+        thread.push(node);
+        node_id = node
+            .get("parent")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+    }
+    thread.reverse();
+
+    let mut messages = vec![];
+    let mut meta = vec![];
+    for node in thread {
+        let message = match node.get("message") {
+            Some(message) if !message.is_null() => message,
+            _ => continue,
+        };
+        let role = message
+            .pointer("/author/role")
+            .and_then(Value::as_str)
+            .unwrap_or("system");
+        let content = message
+            .pointer("/content/parts")
+            .and_then(Value::as_array)
+            .map(|parts| {
+                parts
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<&str>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+        if content.trim().is_empty() {
+            continue;
+        }
+        let timestamp = message
+            .get("create_time")
+            .and_then(Value::as_f64)
+            .and_then(|secs| Utc.timestamp_opt(secs as i64, 0).single());
+        messages.push(saved_message(role, content));
+        meta.push(MessageMeta {
+            timestamp,
+            ..Default::default()
+        });
+    }
+
+    match messages.is_empty() {
+        true => None,
+        false => Some(ImportedChat {
+            name,
+            messages,
+            meta,
+            config: None,
+        }),
+    }
+}
@@ -0,0 +1,80 @@
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Once a journal holds this many entries its owner should fold it into a snapshot
+pub const COMPACT_AFTER: usize = 100;
+
+/// Append-only JSON lines file. Each mutation is written as it happens so a crash loses
+/// at most the line being written, which `read_entries` then skips
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    entries: usize,
+}
+
+impl Journal {
+    pub fn open(path: PathBuf) -> Self {
+        let entries = std::fs::read_to_string(&path)
+            .map(|contents| contents.lines().count())
+            .unwrap_or(0);
+        Self { path, entries }
+    }
+
+    pub fn append<E: Serialize>(&mut self, entry: &E) {
+        if let Err(err) = self.try_append(entry) {
+            tracing::warn!("Failed to append to {}: {:?}", self.path.display(), err);
+        }
+    }
+
+    fn try_append<E: Serialize>(&mut self, entry: &E) -> anyhow::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+        // A single write per line, once it returns the kernel has it even if we're killed
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?
+            .write_all(line.as_bytes())?;
+        self.entries += 1;
+        Ok(())
+    }
+
+    pub fn needs_compaction(&self) -> bool {
+        self.entries >= COMPACT_AFTER
+    }
+
+    /// Call once everything in the journal is safely in a snapshot
+    pub fn truncate(&mut self) {
+        if self.path.exists() {
+            if let Err(err) = std::fs::write(&self.path, "") {
+                tracing::warn!("Failed to truncate {}: {:?}", self.path.display(), err);
+                return;
+            }
+        }
+        self.entries = 0;
+    }
+}
+
+pub fn read_entries<E: DeserializeOwned>(path: &Path) -> Vec<E> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(_) => return vec![],
+    };
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                tracing::warn!("Skipping unreadable line in {}: {:?}", path.display(), err);
+                None
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,57 @@
+use super::{
+    app_dir,
+    journal::{read_entries, Journal},
+    SavedMessage,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::Mutex};
+
+const SPOOL_FILE: &str = "ltm_spool.jsonl";
+
+/// Chat threads append while the backend replays, so every access goes through this
+static SPOOL_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+/// A message that would have gone to long term memory while the database was down
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpooledWrite {
+    pub thread: String,
+    pub message: SavedMessage,
+}
+
+/// Shared by every workspace, like the database it's waiting on
+pub fn spool_file() -> PathBuf {
+    app_dir().join(SPOOL_FILE)
+}
+
+pub fn spool(write: &SpooledWrite) {
+    let _lock = SPOOL_LOCK.lock().unwrap();
+    Journal::open(spool_file()).append(write);
+}
+
+/// Everything spooled so far, oldest first. Writes stay in the spool until
+/// `remove_replayed` is told they made it to the database
+pub fn spooled() -> Vec<SpooledWrite> {
+    let _lock = SPOOL_LOCK.lock().unwrap();
+    read_entries(&spool_file())
+}
+
+/// Drops the oldest `count` writes, keeping whatever was spooled since they were read.
+/// The rest goes through a temp file, so a crash part way leaves the old spool in place
+/// and at worst replays a write twice
+pub fn remove_replayed(count: usize) -> anyhow::Result<()> {
+    if count == 0 {
+        return Ok(());
+    }
+    let _lock = SPOOL_LOCK.lock().unwrap();
+    let path = spool_file();
+    let mut remaining = String::new();
+    for write in read_entries::<SpooledWrite>(&path).iter().skip(count) {
+        remaining.push_str(&serde_json::to_string(write)?);
+        remaining.push('\n');
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    std::fs::write(&tmp, remaining)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
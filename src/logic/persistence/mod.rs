@@ -0,0 +1,108 @@
+pub mod chats;
+pub mod database;
+pub mod export;
+pub mod import;
+pub mod journal;
+pub mod ltm_spool;
+pub mod presets;
+pub mod prompts;
+pub mod settings;
+pub mod startup;
+pub mod workspaces;
+
+use espionox::memory::{Message, MessageRole, MessageVector};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const APP_DIR_NAME: &str = "espionox_egui_demo";
+
+/// Root of everything the app writes to disk
+pub fn app_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(APP_DIR_NAME)
+}
+
+/// Where the current workspace keeps its chats, prompts, presets and settings
+pub fn data_dir() -> PathBuf {
+    let dir = workspaces::workspace_dir(&workspaces::current_workspace());
+    if let Err(err) = std::fs::create_dir_all(&dir) {
+        tracing::warn!(
+            "Couldn't create data directory {}: {:?}",
+            dir.display(),
+            err
+        );
+    }
+    dir
+}
+
+/// A readable file name for a chat or workspace. Different names can share a slug, so it
+/// isn't used where that would mix up data, see `dir_name`
+pub fn slug(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_alphanumeric() || c == '-' || c == '_' {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
+
+/// Chat names become directory names. Percent-encoding keeps every name apart, uppercase
+/// included so names that only differ in case don't meet on case-insensitive filesystems
+pub fn dir_name(name: &str) -> String {
+    if name.is_empty() {
+        return "%".to_string();
+    }
+    name.bytes()
+        .map(|byte| match byte {
+            b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedMessage {
+    pub role: String,
+    pub content: String,
+}
+
+pub fn role_to_str(role: &MessageRole) -> &'static str {
+    match role {
+        MessageRole::User => "user",
+        MessageRole::Assistant => "assistant",
+        _ => "system",
+    }
+}
+
+pub fn role_from_str(role: &str) -> MessageRole {
+    match role.to_lowercase().as_str() {
+        "user" | "human" => MessageRole::User,
+        "assistant" | "ai" | "model" => MessageRole::Assistant,
+        _ => MessageRole::System,
+    }
+}
+
+impl From<&Message> for SavedMessage {
+    fn from(value: &Message) -> Self {
+        Self {
+            role: role_to_str(&value.role()).to_string(),
+            content: value.content().unwrap_or_default(),
+        }
+    }
+}
+
+impl From<&SavedMessage> for Message {
+    fn from(value: &SavedMessage) -> Self {
+        Message::new_standard(role_from_str(&value.role), &value.content)
+    }
+}
+
+pub fn saved_messages(messages: &MessageVector) -> Vec<SavedMessage> {
+    messages.as_ref().iter().map(SavedMessage::from).collect()
+}
+
+pub fn message_vector(messages: &[SavedMessage]) -> MessageVector {
+    let messages: Vec<Message> = messages.iter().map(Message::from).collect();
+    messages.into()
+}
@@ -0,0 +1,84 @@
+use super::{data_dir, saved_messages, SavedMessage};
+use crate::logic::backend::config::AgentConfig;
+use espionox::memory::MessageVector;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+const PRESETS_FILE: &str = "presets.yaml";
+
+/// Everything the New Chat form holds apart from the chat's name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentPreset {
+    pub name: String,
+    #[serde(default)]
+    pub init_prompt: Vec<SavedMessage>,
+    #[serde(default)]
+    pub config: AgentConfig,
+}
+
+impl AgentPreset {
+    pub fn new(name: &str, init_prompt: &MessageVector, config: AgentConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            init_prompt: saved_messages(init_prompt),
+            config,
+        }
+    }
+
+    pub fn init_prompt(&self) -> MessageVector {
+        super::message_vector(&self.init_prompt)
+    }
+}
+
+pub fn presets_file() -> PathBuf {
+    data_dir().join(PRESETS_FILE)
+}
+
+/// A file that exists but can't be parsed is an error rather than no presets, so saving
+/// one doesn't overwrite the others
+pub fn load_presets() -> anyhow::Result<Vec<AgentPreset>> {
+    let path = presets_file();
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+    serde_yaml::from_str(&contents)
+        .map_err(|err| anyhow::anyhow!("{} isn't a valid presets file: {}", path.display(), err))
+}
+
+pub fn find_preset(name: &str) -> Option<AgentPreset> {
+    match load_presets() {
+        Ok(presets) => presets.into_iter().find(|p| p.name == name),
+        Err(err) => {
+            tracing::warn!("Couldn't load presets: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Adds the preset, replacing any existing one with the same name
+pub fn save_preset(preset: AgentPreset) -> anyhow::Result<Vec<AgentPreset>> {
+    let mut presets = load_presets()?;
+    match presets.iter_mut().find(|p| p.name == preset.name) {
+        Some(existing) => *existing = preset,
+        None => presets.push(preset),
+    }
+    write_presets(&presets)?;
+    Ok(presets)
+}
+
+pub fn remove_preset(name: &str) -> anyhow::Result<Vec<AgentPreset>> {
+    let mut presets = load_presets()?;
+    presets.retain(|p| p.name != name);
+    write_presets(&presets)?;
+    Ok(presets)
+}
+
+fn write_presets(presets: &[AgentPreset]) -> anyhow::Result<()> {
+    let path = presets_file();
+    let tmp = path.with_extension("yaml.tmp");
+    std::fs::write(&tmp, serde_yaml::to_string(presets)?)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
@@ -0,0 +1,209 @@
+use super::{data_dir, saved_messages, SavedMessage};
+use espionox::{
+    memory::MessageVector,
+    persistance::prompts::{get_prompts_from_file, Prompt},
+};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, time::SystemTime};
+
+const PROMPTS_FILE: &str = "prompts.yaml";
+
+/// A named init prompt in the app's prompts library
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedPrompt {
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub messages: Vec<SavedMessage>,
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PromptSaveError {
+    #[error("A prompt named '{0}' already exists")]
+    NameTaken(String),
+    #[error("'{0}' was changed on disk since it was loaded")]
+    Conflict(String),
+    #[error("'{0}' is no longer in the prompts file")]
+    NotFound(String),
+    #[error("Not saved, the prompts file couldn't be read: {0}")]
+    Unreadable(anyhow::Error),
+    #[error("Couldn't write prompts file: {0}")]
+    Io(#[from] anyhow::Error),
+}
+
+impl From<&Prompt> for SavedPrompt {
+    fn from(value: &Prompt) -> Self {
+        let messages: MessageVector = value.messages.to_owned().into();
+        Self {
+            name: value.name.to_owned(),
+            tags: vec![],
+            messages: saved_messages(&messages),
+        }
+    }
+}
+
+impl SavedPrompt {
+    pub fn new(name: &str, messages: &MessageVector) -> Self {
+        Self {
+            name: name.to_string(),
+            tags: vec![],
+            messages: saved_messages(messages),
+        }
+    }
+
+    pub fn message_vector(&self) -> MessageVector {
+        super::message_vector(&self.messages)
+    }
+}
+
+pub fn prompts_file() -> PathBuf {
+    data_dir().join(PROMPTS_FILE)
+}
+
+/// When the prompts file was last written, `None` if it doesn't exist yet
+pub fn prompts_modified() -> Option<SystemTime> {
+    std::fs::metadata(prompts_file())
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+/// The library is the app's own file in the data directory, not the one espionox's
+/// `get_prompts_from_file` reads: that one belongs to espionox, and a workspace gets a
+/// library of its own. The first time the library is read it's seeded with espionox's
+/// prompts, after that the two files are independent.
+///
+/// A file that exists but can't be parsed is an error rather than an empty library, since
+/// anything saved on top of it would overwrite the prompts in it
+pub fn load_prompts() -> anyhow::Result<Vec<SavedPrompt>> {
+    let path = prompts_file();
+    match std::fs::read_to_string(&path) {
+        Ok(contents) => serde_yaml::from_str(&contents).map_err(|err| {
+            anyhow::anyhow!("{} isn't a valid prompts file: {}", path.display(), err)
+        }),
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        Err(_) => {
+            let seeded: Vec<SavedPrompt> = get_prompts_from_file()
+                .map(|prompts| prompts.iter().map(SavedPrompt::from).collect())
+                .unwrap_or_default();
+            if let Err(err) = save_prompts(&seeded) {
+                tracing::warn!("Couldn't seed prompts file: {:?}", err);
+            }
+            Ok(seeded)
+        }
+    }
+}
+
+pub fn save_prompts(prompts: &[SavedPrompt]) -> anyhow::Result<()> {
+    let path = prompts_file();
+    let tmp = path.with_extension("yaml.tmp");
+    std::fs::write(&tmp, serde_yaml::to_string(prompts)?)?;
+    std::fs::rename(tmp, path)?;
+    Ok(())
+}
+
+/// Adds a new prompt, refusing to clobber one that already has the name
+pub fn save_prompt_as(prompt: SavedPrompt) -> Result<(), PromptSaveError> {
+    let mut prompts = load_prompts().map_err(PromptSaveError::Unreadable)?;
+    if prompts.iter().any(|p| p.name == prompt.name) {
+        return Err(PromptSaveError::NameTaken(prompt.name));
+    }
+    prompts.push(prompt);
+    Ok(save_prompts(&prompts)?)
+}
+
+/// Overwrites `original` with `updated`. If what's on disk no longer matches `original`
+/// someone else has edited it, so unless `force` is set the update is refused
+pub fn update_prompt(
+    original: &SavedPrompt,
+    updated: SavedPrompt,
+    force: bool,
+) -> Result<(), PromptSaveError> {
+    let mut prompts = load_prompts().map_err(PromptSaveError::Unreadable)?;
+    let position = prompts.iter().position(|p| p.name == original.name);
+    match position {
+        Some(idx) if prompts[idx] == *original || force => {
+            prompts[idx] = updated;
+        }
+        None if force => prompts.push(updated),
+        _ => return Err(PromptSaveError::Conflict(original.name.to_owned())),
+    }
+    Ok(save_prompts(&prompts)?)
+}
+
+/// Loads the library fresh, applies `edit` and writes the result back
+pub fn edit_prompts(
+    edit: impl FnOnce(&mut Vec<SavedPrompt>) -> Result<(), PromptSaveError>,
+) -> Result<Vec<SavedPrompt>, PromptSaveError> {
+    let mut prompts = load_prompts().map_err(PromptSaveError::Unreadable)?;
+    edit(&mut prompts)?;
+    save_prompts(&prompts)?;
+    Ok(prompts)
+}
+
+/// `name`, or `name (2)`, `name (3)`… if that's taken
+pub fn unique_prompt_name(name: &str, prompts: &[SavedPrompt]) -> String {
+    let mut candidate = name.to_string();
+    let mut n = 2;
+    while prompts.iter().any(|p| p.name == candidate) {
+        candidate = format!("{} ({})", name, n);
+        n += 1;
+    }
+    candidate
+}
+
+/// A shareable bundle of prompts. Plain lists, like the library file itself, are also
+/// accepted on import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptPack {
+    pub prompts: Vec<SavedPrompt>,
+}
+
+pub fn read_prompt_pack(path: &std::path::Path) -> anyhow::Result<Vec<SavedPrompt>> {
+    let contents = std::fs::read_to_string(path)?;
+    match serde_yaml::from_str::<PromptPack>(&contents) {
+        Ok(pack) => Ok(pack.prompts),
+        Err(_) => Ok(serde_yaml::from_str::<Vec<SavedPrompt>>(&contents)?),
+    }
+}
+
+pub fn write_prompt_pack(path: &std::path::Path, prompts: Vec<SavedPrompt>) -> anyhow::Result<()> {
+    std::fs::write(path, serde_yaml::to_string(&PromptPack { prompts })?)?;
+    Ok(())
+}
+
+/// Cheap check for whether the prompts file changed since it was last read. Only stats
+/// the file once a second so it can be polled every frame
+#[derive(Debug)]
+pub struct PromptsWatcher {
+    seen: Option<SystemTime>,
+    last_check: std::time::Instant,
+}
+
+impl PromptsWatcher {
+    pub fn new() -> Self {
+        Self {
+            seen: prompts_modified(),
+            last_check: std::time::Instant::now(),
+        }
+    }
+
+    pub fn changed(&mut self) -> bool {
+        if self.last_check.elapsed() < std::time::Duration::from_secs(1) {
+            return false;
+        }
+        self.last_check = std::time::Instant::now();
+        let modified = prompts_modified();
+        match modified != self.seen {
+            true => {
+                self.seen = modified;
+                true
+            }
+            false => false,
+        }
+    }
+
+    /// Call after writing the file ourselves so it isn't reported as an outside change
+    pub fn mark_seen(&mut self) {
+        self.seen = prompts_modified();
+    }
+}
@@ -0,0 +1,27 @@
+use super::data_dir;
+use crate::logic::backend::settings::CompletionSettings;
+use std::path::PathBuf;
+
+const SETTINGS_FILE: &str = "settings.yaml";
+
+pub fn settings_file() -> PathBuf {
+    data_dir().join(SETTINGS_FILE)
+}
+
+pub fn load_completion_settings() -> CompletionSettings {
+    std::fs::read_to_string(settings_file())
+        .ok()
+        .and_then(|contents| match serde_yaml::from_str(&contents) {
+            Ok(settings) => Some(settings),
+            Err(err) => {
+                tracing::warn!("Couldn't parse settings file: {:?}", err);
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+pub fn save_completion_settings(settings: &CompletionSettings) -> anyhow::Result<()> {
+    std::fs::write(settings_file(), serde_yaml::to_string(settings)?)?;
+    Ok(())
+}
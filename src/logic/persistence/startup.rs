@@ -0,0 +1,122 @@
+use super::{data_dir, presets::find_preset};
+use crate::logic::backend::config::AgentConfig;
+use espionox::memory::MessageVector;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeSet, path::PathBuf};
+
+const STARTUP_FILE: &str = "startup.yaml";
+const SEEDED_FILE: &str = "startup_seeded.json";
+
+/// Chats created on launch. Each is only created once per workspace, so one that's been
+/// deleted stays deleted, see `seeded_startup_chats`. An empty list is fine, the app then
+/// starts with no chats at all
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupConfig {
+    #[serde(default)]
+    pub chats: Vec<StartupChat>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartupChat {
+    pub name: String,
+    /// Name of an entry in the presets file
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Uses a long term thread named after the chat, which needs the database
+    #[serde(default)]
+    pub long_term_memory: bool,
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        Self {
+            chats: vec![
+                StartupChat {
+                    name: "Chat Agent".to_string(),
+                    preset: None,
+                    long_term_memory: false,
+                },
+                StartupChat {
+                    name: "Long Term Agent".to_string(),
+                    preset: None,
+                    long_term_memory: true,
+                },
+            ],
+        }
+    }
+}
+
+impl StartupChat {
+    /// The preset's config and init prompt, if it has one that exists
+    pub fn agent(&self) -> (AgentConfig, MessageVector) {
+        let preset = self.preset.as_ref().and_then(|name| {
+            let preset = find_preset(name);
+            if preset.is_none() {
+                tracing::warn!("Startup chat {} uses unknown preset {}", self.name, name);
+            }
+            preset
+        });
+        let (mut config, init_prompt) = match preset {
+            Some(preset) => (preset.config.to_owned(), preset.init_prompt()),
+            None => (AgentConfig::default(), MessageVector::init()),
+        };
+        config.long_term_thread = match self.long_term_memory {
+            true => config.long_term_thread.or(Some(self.name.to_owned())),
+            false => None,
+        };
+        (config, init_prompt)
+    }
+}
+
+pub fn startup_file() -> PathBuf {
+    data_dir().join(STARTUP_FILE)
+}
+
+/// Writes out the defaults the first time so there's a file to edit
+pub fn load_startup_config() -> StartupConfig {
+    match std::fs::read_to_string(startup_file()) {
+        Ok(contents) => match serde_yaml::from_str(&contents) {
+            Ok(config) => config,
+            Err(err) => {
+                tracing::warn!(
+                    "Couldn't parse startup file, starting with no chats: {:?}",
+                    err
+                );
+                StartupConfig { chats: vec![] }
+            }
+        },
+        Err(_) => {
+            let config = StartupConfig::default();
+            match serde_yaml::to_string(&config) {
+                Ok(yaml) => {
+                    if let Err(err) = std::fs::write(startup_file(), yaml) {
+                        tracing::warn!("Couldn't write startup file: {:?}", err);
+                    }
+                }
+                Err(err) => tracing::warn!("Couldn't serialize startup file: {:?}", err),
+            }
+            config
+        }
+    }
+}
+
+/// Startup chats that have already been created in this workspace. Taking a name out of
+/// the file has that chat created again on the next launch
+pub fn seeded_startup_chats() -> BTreeSet<String> {
+    let contents = match std::fs::read_to_string(data_dir().join(SEEDED_FILE)) {
+        Ok(contents) => contents,
+        Err(_) => return BTreeSet::new(),
+    };
+    serde_json::from_str(&contents).unwrap_or_else(|err| {
+        tracing::warn!("Couldn't parse {}: {:?}", SEEDED_FILE, err);
+        BTreeSet::new()
+    })
+}
+
+pub fn save_seeded_startup_chats(names: &BTreeSet<String>) -> anyhow::Result<()> {
+    std::fs::write(
+        data_dir().join(SEEDED_FILE),
+        serde_json::to_string_pretty(names)?,
+    )?;
+    Ok(())
+}
@@ -0,0 +1,99 @@
+use super::{app_dir, dir_name, slug};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::RwLock};
+
+const WORKSPACES_FILE: &str = "workspaces.yaml";
+pub const DEFAULT_WORKSPACE: &str = "Default";
+
+/// Which workspaces exist and which one was open last
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WorkspacesFile {
+    current: String,
+    workspaces: Vec<String>,
+}
+
+impl Default for WorkspacesFile {
+    fn default() -> Self {
+        Self {
+            current: DEFAULT_WORKSPACE.to_string(),
+            workspaces: vec![DEFAULT_WORKSPACE.to_string()],
+        }
+    }
+}
+
+static CURRENT_WORKSPACE: Lazy<RwLock<String>> = Lazy::new(|| RwLock::new(read_file().current));
+
+fn read_file() -> WorkspacesFile {
+    std::fs::read_to_string(app_dir().join(WORKSPACES_FILE))
+        .ok()
+        .and_then(|contents| serde_yaml::from_str(&contents).ok())
+        .unwrap_or_default()
+}
+
+fn write_file(file: &WorkspacesFile) -> anyhow::Result<()> {
+    std::fs::create_dir_all(app_dir())?;
+    std::fs::write(
+        app_dir().join(WORKSPACES_FILE),
+        serde_yaml::to_string(file)?,
+    )?;
+    Ok(())
+}
+
+/// The default workspace lives at the root of the app directory, so data written before
+/// workspaces existed stays where it was
+pub fn workspace_dir(name: &str) -> PathBuf {
+    if name == DEFAULT_WORKSPACE {
+        return app_dir();
+    }
+    let workspaces = app_dir().join("workspaces");
+    let dir = workspaces.join(dir_name(name));
+    let legacy = workspaces.join(slug(name));
+    if !dir.exists() && legacy != dir && legacy.exists() && owns_legacy_dir(name) {
+        if let Err(err) = std::fs::rename(&legacy, &dir) {
+            tracing::warn!(
+                "Couldn't move {} workspace to {}: {:?}",
+                name,
+                dir.display(),
+                err
+            );
+            return legacy;
+        }
+    }
+    dir
+}
+
+/// Workspace directories used to be slugs, which don't record the name they came from. One
+/// only moves when no other workspace slugs to it, otherwise it's left alone rather than
+/// handed to the wrong one
+fn owns_legacy_dir(name: &str) -> bool {
+    list_workspaces()
+        .iter()
+        .filter(|w| w.as_str() != DEFAULT_WORKSPACE && slug(w) == slug(name))
+        .all(|w| w == name)
+}
+
+pub fn current_workspace() -> String {
+    CURRENT_WORKSPACE.read().unwrap().to_owned()
+}
+
+pub fn list_workspaces() -> Vec<String> {
+    let mut workspaces = read_file().workspaces;
+    if !workspaces.iter().any(|w| w == DEFAULT_WORKSPACE) {
+        workspaces.insert(0, DEFAULT_WORKSPACE.to_string());
+    }
+    workspaces
+}
+
+/// Everything written through `data_dir` goes to this workspace from now on, creating it
+/// if it's new
+pub fn set_current_workspace(name: &str) -> anyhow::Result<()> {
+    let mut file = read_file();
+    if !file.workspaces.iter().any(|w| w == name) {
+        file.workspaces.push(name.to_string());
+    }
+    file.current = name.to_string();
+    write_file(&file)?;
+    *CURRENT_WORKSPACE.write().unwrap() = name.to_string();
+    Ok(())
+}
@@ -3,6 +3,10 @@ use eframe::egui;
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum State {
     Chat,
+    Compare,
+    Dialogue,
+    Prompts,
+    Memory,
     Settings,
 }
 
@@ -26,6 +30,13 @@ impl Default for State {
 
 impl State {
     pub fn all() -> Vec<Self> {
-        vec![State::Chat, State::Settings]
+        vec![
+            State::Chat,
+            State::Compare,
+            State::Dialogue,
+            State::Prompts,
+            State::Memory,
+            State::Settings,
+        ]
     }
 }
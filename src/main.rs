@@ -16,8 +16,10 @@ static TRACING: Lazy<()> = Lazy::new(|| {
     // }
 });
 
-#[tokio::main]
-async fn main() {
+// Not `#[tokio::main]`, the environment has to be set before any other thread exists.
+// `MainApplication` starts its own runtime
+fn main() {
     Lazy::force(&TRACING);
+    persistence::database::load_database_settings().apply_to_env();
     MainApplication::run().expect("Failed to run ap");
 }